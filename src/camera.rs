@@ -1,11 +1,23 @@
 use glm::Vec3;
 
+use crate::uniform_block;
+
 const YAW: f32 = -90.;
 const PITCH: f32 = 0.;
 const SPEED: f32 = 2.5;
 const SENSITIVITY: f32 = 0.1;
 const ZOOM: f32 = 45.;
 
+uniform_block! {
+    /// Per-frame camera data shared by every shader through `shader/camera.glsl`.
+    pub struct CameraBlock: "Camera" {
+        pub view: glm::Mat4,
+        pub projection: glm::Mat4,
+        pub position: glm::Vec3,
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub position: Vec3,
     pub front: Vec3,
@@ -0,0 +1,367 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+
+use glfw::{Action, Context, Key, Modifiers, Window, WindowEvent};
+
+use crate::frame_pacer::{FramePacer, FrameRate};
+use crate::gamepad::{DeadZones, GamepadEvent, Gamepads};
+use crate::gl_debug;
+use crate::replay::{Recorder, Replay, ReplayError};
+use crate::utils::Input;
+use crate::window_mode::{MonitorChoice, WindowMode, WindowModeError, WindowModes};
+
+/// The hooks an application implements to be driven by [`Engine::run`].
+pub trait Application: Sized {
+    /// Creates the application once the window and its GL context exist, so GL resources can
+    /// be created here.
+    fn init(window: &mut Window) -> Self;
+
+    /// Advances the simulation by one tick of `dt` seconds, which is always
+    /// `1 / EngineConfig::update_rate`. It runs zero or more times per frame.
+    fn update(&mut self, input: &Input, dt: f32);
+
+    /// Draws the current state. `alpha` in `[0, 1)` is how far the time not yet simulated is
+    /// into the next tick, for blending the last two ticks. The engine swaps the buffers
+    /// afterwards.
+    fn render(&mut self, alpha: f32);
+
+    /// Called after the framebuffer changed size or moved to a display with another content
+    /// scale, once the viewport covers the new framebuffer. Size-dependent resources like
+    /// render targets and the projection should be recreated here. Not called while the window
+    /// is minimized and its framebuffer is empty.
+    fn resize(&mut self, _surface: &Surface) {}
+
+    /// Called when a gamepad is connected or disconnected. [`Input`] follows the gamepad that
+    /// was connected first.
+    fn on_gamepad(&mut self, _event: &GamepadEvent) {}
+
+    /// Sees every window event before it is folded into [`Input`]. Call
+    /// `window.set_should_close(true)` to stop the engine.
+    fn on_event(&mut self, _window: &mut Window, _event: &WindowEvent) {}
+}
+
+/// Size of the window and its framebuffer.
+///
+/// On HiDPI displays with macOS and Wayland the framebuffer has more pixels than the window
+/// has screen coordinates, so the two sizes differ. Rendering works in framebuffer pixels,
+/// while cursor positions are in screen coordinates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Surface {
+    /// Size in pixels, for the viewport and render targets.
+    pub framebuffer_size: (u32, u32),
+    /// Size in screen coordinates.
+    pub window_size: (u32, u32),
+    /// Ratio between the DPI of the current display and the platform default, for sizing
+    /// text and UI. It can be above 1 even where framebuffer and window sizes are equal, as on
+    /// Windows.
+    pub content_scale: (f32, f32),
+}
+
+impl Surface {
+    pub fn from_window(window: &Window) -> Self {
+        let (width, height) = window.get_framebuffer_size();
+        let (window_width, window_height) = window.get_size();
+        Self {
+            framebuffer_size: (width.max(0) as u32, height.max(0) as u32),
+            window_size: (window_width.max(0) as u32, window_height.max(0) as u32),
+            content_scale: window.get_content_scale(),
+        }
+    }
+
+    /// Width over height of the framebuffer, or 1 when it is empty.
+    pub fn aspect_ratio(&self) -> f32 {
+        let (width, height) = self.framebuffer_size;
+        if width == 0 || height == 0 {
+            return 1.;
+        }
+        width as f32 / height as f32
+    }
+
+    fn is_empty(&self) -> bool {
+        self.framebuffer_size.0 == 0 || self.framebuffer_size.1 == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub title: String,
+    /// Size of the window in windowed mode.
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowMode,
+    /// The mode Alt+Enter switches to from windowed mode and back.
+    pub fullscreen_mode: WindowMode,
+    /// Requests a GL debug context, which makes drivers report far more through the debug
+    /// callback but is slower.
+    pub debug_context: bool,
+    /// Lowest severity of the driver messages logged in a debug context.
+    pub debug_severity: gl_debug::Severity,
+    pub frame_rate: FrameRate,
+    /// Simulation ticks per second.
+    pub update_rate: f32,
+    /// Most ticks run in one frame. When a frame took longer, for example after a stall, the
+    /// rest of the time is dropped, so the simulation slows down instead of spiralling.
+    pub max_updates_per_frame: u32,
+    pub gamepad_dead_zones: DeadZones,
+    /// Reads unscaled and unaccelerated mouse motion while the cursor is disabled, where the
+    /// platform supports it.
+    pub raw_mouse_motion: bool,
+    /// Writes the input of every update to this file.
+    pub record: Option<PathBuf>,
+    /// Runs the updates with the input recorded in this file instead of the devices, at the
+    /// recorded update rate, and closes the window once it ran out.
+    pub replay: Option<PathBuf>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            title: "GL Engine".to_string(),
+            width: 1400,
+            height: 900,
+            window_mode: WindowMode::Windowed,
+            fullscreen_mode: WindowMode::Borderless {
+                monitor: MonitorChoice::Primary,
+            },
+            debug_context: false,
+            debug_severity: gl_debug::Severity::Low,
+            frame_rate: FrameRate::VSync,
+            update_rate: 60.,
+            max_updates_per_frame: 5,
+            gamepad_dead_zones: DeadZones::default(),
+            raw_mouse_motion: true,
+            record: None,
+            replay: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Init(glfw::InitError),
+    WindowCreation,
+    WindowMode(WindowModeError),
+    Replay(ReplayError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Init(err) => write!(f, "cannot initialize glfw: {:?}", err),
+            EngineError::WindowCreation => f.write_str("window creation failed"),
+            EngineError::WindowMode(err) => write!(f, "cannot set the window mode: {}", err),
+            EngineError::Replay(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Owns the window, its GL context and the main loop.
+pub struct Engine {
+    glfw: glfw::Glfw,
+    window: Window,
+    events: Receiver<(f64, WindowEvent)>,
+    modes: WindowModes,
+    pacer: FramePacer,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    config: EngineConfig,
+}
+
+impl Engine {
+    /// Opens the window and makes its OpenGL 3.3 core context current.
+    pub fn new(config: EngineConfig) -> Result<Self, EngineError> {
+        let replay = config
+            .replay
+            .as_ref()
+            .map(Replay::open)
+            .transpose()
+            .map_err(EngineError::Replay)?;
+        let recorder = config
+            .record
+            .as_ref()
+            .map(|path| Recorder::create(path, config.update_rate))
+            .transpose()
+            .map_err(EngineError::Replay)?;
+
+        let mut glfw = glfw::init(glfw::LOG_ERRORS).map_err(EngineError::Init)?;
+        glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
+        glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+            glfw::OpenGlProfileHint::Core,
+        ));
+        glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(config.debug_context));
+        // Keeps a fullscreen window on a projector or second screen up while the user works on
+        // another monitor.
+        glfw.window_hint(glfw::WindowHint::AutoIconify(false));
+
+        let (mut window, events) = glfw
+            .create_window(
+                config.width,
+                config.height,
+                &config.title,
+                glfw::WindowMode::Windowed,
+            )
+            .ok_or(EngineError::WindowCreation)?;
+
+        let mut modes = WindowModes::new(&window, config.fullscreen_mode.clone());
+        modes
+            .set(&mut window, config.window_mode.clone())
+            .map_err(EngineError::WindowMode)?;
+
+        window.make_current();
+        window.set_framebuffer_size_polling(true);
+        window.set_size_polling(true);
+        window.set_content_scale_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_scroll_polling(true);
+        window.set_key_polling(true);
+        if config.raw_mouse_motion {
+            if glfw.supports_raw_motion() {
+                window.set_raw_mouse_motion(true);
+            } else {
+                log::info!("raw mouse motion is not supported, using the cursor motion");
+            }
+        }
+
+        gl::load_with(|symbol| window.get_proc_address(symbol));
+        if config.debug_context && !gl_debug::enable(config.debug_severity) {
+            log::warn!("a debug context was requested but it has no debug output");
+        }
+
+        let pacer = FramePacer::new(&mut glfw, config.frame_rate);
+
+        Ok(Self {
+            glfw,
+            window,
+            events,
+            modes,
+            pacer,
+            recorder,
+            replay,
+            config,
+        })
+    }
+
+    /// Creates the application and runs the loop until the window is asked to close.
+    pub fn run<A: Application>(mut self) {
+        let mut app = A::init(&mut self.window);
+
+        let (x, y) = self.window.get_cursor_pos();
+        let mut input = Input::new(x as f32, y as f32);
+        let update_rate = match &self.replay {
+            Some(replay) => replay.update_rate(),
+            None => self.config.update_rate,
+        };
+        // Time is kept in f64: after hours of uptime an f32 second count only has millisecond
+        // steps, which would jitter the tick count and the interpolation.
+        let tick = 1. / f64::from(update_rate);
+        let dt = tick as f32;
+        input.delta_time = dt;
+        input.gamepad_dead_zones = self.config.gamepad_dead_zones;
+        let mut gamepads = Gamepads::new();
+        let mut gamepad_events = Vec::new();
+        let mut accumulator = 0.;
+        let mut last_frame = self.glfw.get_time();
+        let mut last_report = last_frame;
+        let mut surface = Surface::from_window(&self.window);
+
+        while !self.window.should_close() {
+            let seconds = self.glfw.get_time();
+            accumulator += seconds - last_frame;
+            last_frame = seconds;
+
+            // Events are folded into `input` until a tick consumes them, so nothing is lost on
+            // frames without a tick.
+            let mut resized = false;
+            for (_, event) in glfw::flush_messages(&self.events) {
+                if let WindowEvent::FramebufferSize(..)
+                | WindowEvent::Size(..)
+                | WindowEvent::ContentScale(..) = event
+                {
+                    resized = true;
+                }
+                if let WindowEvent::Key(Key::Enter, _, Action::Press, modifiers) = event {
+                    if modifiers.contains(Modifiers::Alt) {
+                        if let Err(err) = self.modes.toggle(&mut self.window) {
+                            log::error!("{}", err);
+                        }
+                    }
+                }
+                app.on_event(&mut self.window, &event);
+                input.process_event(&event);
+            }
+            let gamepad = gamepads.poll(&self.glfw, &mut gamepad_events);
+            for event in gamepad_events.drain(..) {
+                app.on_gamepad(&event);
+            }
+            input.process_gamepad(gamepad.as_ref());
+
+            if resized {
+                // The events only carry one of the sizes each, so read all of them at once.
+                let new_surface = Surface::from_window(&self.window);
+                if new_surface != surface && !new_surface.is_empty() {
+                    surface = new_surface;
+                    let (width, height) = surface.framebuffer_size;
+                    unsafe {
+                        gl::Viewport(0, 0, width as i32, height as i32);
+                    }
+                    app.resize(&surface);
+                }
+            }
+
+            let mut updates = 0;
+            while accumulator >= tick && updates < self.config.max_updates_per_frame {
+                if let Some(replay) = &mut self.replay {
+                    let Some(recorded) = replay.next() else {
+                        log::info!("replay finished");
+                        self.window.set_should_close(true);
+                        break;
+                    };
+                    app.update(&recorded, recorded.delta_time);
+                } else {
+                    if let Some(recorder) = &mut self.recorder {
+                        if let Err(err) = recorder.record(&input) {
+                            log::error!("stopping the recording: {}", err);
+                            self.recorder = None;
+                        }
+                    }
+                    app.update(&input, dt);
+                    input.advance(dt);
+                }
+                accumulator -= tick;
+                updates += 1;
+            }
+            if accumulator >= tick {
+                log::debug!(
+                    "simulation fell behind, dropping {:.1} ms",
+                    (accumulator - accumulator % tick) * 1000.
+                );
+                accumulator %= tick;
+            }
+
+            app.render((accumulator / tick) as f32);
+
+            self.pacer.wait();
+            self.window.swap_buffers();
+
+            if seconds - last_report >= 1. {
+                last_report = seconds;
+                let stats = self.pacer.stats();
+                log::debug!(
+                    target: "frame",
+                    "{:.0} fps, frame time {:.2?} avg, {:.2?} min, {:.2?} max, {:.2?} p99",
+                    stats.fps(),
+                    stats.average(),
+                    stats.min(),
+                    stats.max(),
+                    stats.percentile(99.)
+                );
+            }
+
+            self.glfw.poll_events();
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Decides when frames are presented and keeps statistics about how long they took.
+
+use std::time::{Duration, Instant};
+
+use glfw::{Glfw, SwapInterval};
+
+/// How long before a deadline [`FramePacer`] stops sleeping and spins instead. Sleeps overshoot
+/// by up to the scheduler granularity, which is about a millisecond on Linux and macOS but can
+/// be more on Windows.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(2000);
+
+/// Number of frames [`FrameStats`] keeps.
+const STATS_FRAMES: usize = 120;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameRate {
+    /// Waits for the vertical blank before every swap.
+    VSync,
+    /// Like `VSync`, but a late frame is swapped right away and tears instead of waiting for
+    /// the next blank, which would halve the frame rate. Falls back to `VSync` without
+    /// `WGL_EXT_swap_control_tear` or `GLX_EXT_swap_control_tear`.
+    AdaptiveVSync,
+    /// Swaps as soon as a frame is rendered.
+    Uncapped,
+    /// Presents the given number of frames per second, without vsync. Rates that are not
+    /// positive and finite run `Uncapped`.
+    Target(f32),
+}
+
+/// Paces frames according to a [`FrameRate`].
+///
+/// Call [`wait`](Self::wait) right before swapping the buffers. The pacer needs the context
+/// to be current, because vsync is a property of the context.
+pub struct FramePacer {
+    rate: FrameRate,
+    /// Time between frames in `Target` mode.
+    period: Option<Duration>,
+    deadline: Option<Instant>,
+    last_frame: Instant,
+    stats: FrameStats,
+}
+
+impl FramePacer {
+    pub fn new(glfw: &mut Glfw, rate: FrameRate) -> Self {
+        let mut pacer = Self {
+            rate,
+            period: None,
+            deadline: None,
+            last_frame: Instant::now(),
+            stats: FrameStats::new(),
+        };
+        pacer.set_rate(glfw, rate);
+        pacer
+    }
+
+    pub fn rate(&self) -> FrameRate {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, glfw: &mut Glfw, rate: FrameRate) {
+        self.period = None;
+        let rate = match rate {
+            FrameRate::Target(fps) => match target_period(fps) {
+                Some(period) => {
+                    self.period = Some(period);
+                    rate
+                }
+                None => {
+                    log::warn!("cannot target {} frames per second, running uncapped", fps);
+                    FrameRate::Uncapped
+                }
+            },
+            rate => rate,
+        };
+        let interval = match rate {
+            FrameRate::VSync => SwapInterval::Sync(1),
+            FrameRate::AdaptiveVSync
+                if glfw.extension_supported("WGL_EXT_swap_control_tear")
+                    || glfw.extension_supported("GLX_EXT_swap_control_tear") =>
+            {
+                SwapInterval::Adaptive
+            }
+            FrameRate::AdaptiveVSync => {
+                log::warn!("adaptive vsync is not supported, using vsync");
+                SwapInterval::Sync(1)
+            }
+            FrameRate::Uncapped | FrameRate::Target(_) => SwapInterval::None,
+        };
+        glfw.set_swap_interval(interval);
+        self.rate = rate;
+        self.deadline = None;
+    }
+
+    /// Blocks until the current frame is due in `Target` mode and records the frame time.
+    pub fn wait(&mut self) {
+        if let Some(period) = self.period {
+            let now = Instant::now();
+            // Deadlines follow each other by exactly one period, so the rounding of one wait
+            // does not add up over frames. After a slow frame the schedule restarts instead
+            // of rushing the next frames out to catch up.
+            let deadline = match self.deadline {
+                Some(deadline) if deadline + period > now => deadline,
+                _ => now,
+            };
+            if let Some(sleep) = deadline
+                .checked_duration_since(now)
+                .and_then(|left| left.checked_sub(SPIN_THRESHOLD))
+            {
+                std::thread::sleep(sleep);
+            }
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+            self.deadline = Some(deadline + period);
+        }
+
+        let now = Instant::now();
+        self.stats.record(now - self.last_frame);
+        self.last_frame = now;
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+}
+
+/// Time between frames at `fps`, or `None` for rates that cannot be targeted.
+fn target_period(fps: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32(1. / fps)
+        .ok()
+        .filter(|period| fps > 0. && !period.is_zero())
+}
+
+/// Times of the last frames, from one [`FramePacer::wait`] to the next.
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    times: [Duration; STATS_FRAMES],
+    next: usize,
+    len: usize,
+    frames: u64,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self {
+            times: [Duration::ZERO; STATS_FRAMES],
+            next: 0,
+            len: 0,
+            frames: 0,
+        }
+    }
+
+    fn record(&mut self, time: Duration) {
+        self.times[self.next] = time;
+        self.next = (self.next + 1) % STATS_FRAMES;
+        self.len = (self.len + 1).min(STATS_FRAMES);
+        self.frames += 1;
+    }
+
+    fn recent(&self) -> &[Duration] {
+        &self.times[..self.len]
+    }
+
+    /// Frames presented since the pacer was created.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn last(&self) -> Duration {
+        self.times[(self.next + STATS_FRAMES - 1) % STATS_FRAMES]
+    }
+
+    /// Mean frame time over the last frames.
+    pub fn average(&self) -> Duration {
+        if self.len == 0 {
+            return Duration::ZERO;
+        }
+        self.recent().iter().sum::<Duration>() / self.len as u32
+    }
+
+    pub fn min(&self) -> Duration {
+        self.recent().iter().copied().min().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.recent().iter().copied().max().unwrap_or_default()
+    }
+
+    /// Frame time that `percent` of the last frames stayed under, which shows stutter that
+    /// the average hides.
+    pub fn percentile(&self, percent: f32) -> Duration {
+        if self.len == 0 {
+            return Duration::ZERO;
+        }
+        let mut sorted = self.recent().to_vec();
+        sorted.sort_unstable();
+        let index = ((percent / 100.) * (self.len - 1) as f32).round() as usize;
+        sorted[index.min(self.len - 1)]
+    }
+
+    pub fn fps(&self) -> f32 {
+        let average = self.average().as_secs_f32();
+        if average > 0. {
+            1. / average
+        } else {
+            0.
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(times: &[u64]) -> FrameStats {
+        let mut stats = FrameStats::new();
+        for &time in times {
+            stats.record(Duration::from_millis(time));
+        }
+        stats
+    }
+
+    #[test]
+    fn target_rates_are_turned_into_periods() {
+        let period = target_period(60.).unwrap();
+        assert!(period.abs_diff(Duration::from_secs(1) / 60) < Duration::from_micros(1));
+        assert_eq!(target_period(0.5), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_and_finite_have_no_period() {
+        for fps in [0., -0., -30., f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(target_period(fps), None, "{} fps", fps);
+        }
+    }
+
+    #[test]
+    fn stats_summarize_the_recorded_frames() {
+        let stats = millis(&[10, 40, 20, 30]);
+        assert_eq!(stats.frames(), 4);
+        assert_eq!(stats.last(), Duration::from_millis(30));
+        assert_eq!(stats.average(), Duration::from_millis(25));
+        assert_eq!(stats.fps(), 40.);
+        assert_eq!(stats.min(), Duration::from_millis(10));
+        assert_eq!(stats.max(), Duration::from_millis(40));
+        assert_eq!(stats.percentile(0.), Duration::from_millis(10));
+        assert_eq!(stats.percentile(100.), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn stats_only_average_the_last_frames() {
+        let mut stats = millis(&[100; STATS_FRAMES]);
+        for _ in 0..STATS_FRAMES {
+            stats.record(Duration::from_millis(10));
+        }
+        assert_eq!(stats.frames(), 2 * STATS_FRAMES as u64);
+        assert_eq!(stats.average(), Duration::from_millis(10));
+        assert_eq!(stats.max(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn stats_without_frames_are_zero() {
+        let stats = FrameStats::new();
+        assert_eq!(stats.average(), Duration::ZERO);
+        assert_eq!(stats.percentile(99.), Duration::ZERO);
+        assert_eq!(stats.fps(), 0.);
+    }
+}
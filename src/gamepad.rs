@@ -0,0 +1,178 @@
+//! Gamepads polled through GLFW's gamepad API, which maps the buttons and axes of known
+//! controllers to an Xbox-like layout.
+
+use glfw::{GamepadState, Glfw, JoystickId};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GamepadEvent {
+    Connected { id: JoystickId, name: String },
+    Disconnected { id: JoystickId },
+}
+
+/// Largest dead zone that is applied. A zone of 1 would leave nothing to rescale the rest of
+/// the range to.
+const MAX_DEAD_ZONE: f32 = 0.95;
+
+/// Ranges around the rest position of sticks and triggers that count as not touched, because
+/// worn sticks do not center exactly and triggers rarely rest at exactly 0.
+///
+/// Zones are clamped to `[0, 0.95]`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeadZones {
+    /// Radius around the center, as a fraction of the full deflection.
+    pub stick: f32,
+    pub trigger: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        Self {
+            stick: 0.15,
+            trigger: 0.05,
+        }
+    }
+}
+
+impl DeadZones {
+    /// Zeroes a stick inside the dead zone and rescales the rest, so values still start at 0
+    /// right outside of it and reach 1 at full deflection. The zone is round, so a stick
+    /// pushed slightly off an axis is not snapped onto it.
+    pub fn apply_to_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        let zone = clamp_zone(self.stick);
+        let length = x.hypot(y);
+        if length <= zone {
+            return (0., 0.);
+        }
+        let scaled = ((length - zone) / (1. - zone)).min(1.);
+        (x / length * scaled, y / length * scaled)
+    }
+
+    /// Maps a trigger from GLFW's `[-1, 1]` to `[0, 1]` and applies the dead zone.
+    pub fn apply_to_trigger(&self, value: f32) -> f32 {
+        let zone = clamp_zone(self.trigger);
+        let value = (value + 1.) / 2.;
+        if value <= zone {
+            return 0.;
+        }
+        ((value - zone) / (1. - zone)).min(1.)
+    }
+}
+
+fn clamp_zone(zone: f32) -> f32 {
+    if zone.is_nan() {
+        0.
+    } else {
+        zone.clamp(0., MAX_DEAD_ZONE)
+    }
+}
+
+/// Tracks which gamepads are connected. The one connected first is the one read into
+/// [`Input`](crate::utils::Input), until it is disconnected.
+pub(crate) struct Gamepads {
+    connected: Vec<JoystickId>,
+    /// Joysticks that were reported as having no gamepad mapping.
+    unmapped: Vec<JoystickId>,
+}
+
+impl Gamepads {
+    pub(crate) fn new() -> Self {
+        Self {
+            connected: Vec::new(),
+            unmapped: Vec::new(),
+        }
+    }
+
+    /// Finds the gamepads connected and disconnected since the last poll, and returns the
+    /// state of the active one.
+    pub(crate) fn poll(
+        &mut self,
+        glfw: &Glfw,
+        events: &mut Vec<GamepadEvent>,
+    ) -> Option<GamepadState> {
+        for id in (0..=glfw::ffi::JOYSTICK_LAST).filter_map(JoystickId::from_i32) {
+            let joystick = glfw.get_joystick(id);
+            let was_connected = self.connected.contains(&id);
+            let is_gamepad = joystick.is_gamepad();
+            if is_gamepad && !was_connected {
+                let name = joystick.get_gamepad_name().unwrap_or_default();
+                log::info!("gamepad {:?} connected: {}", id, name);
+                self.connected.push(id);
+                events.push(GamepadEvent::Connected { id, name });
+            } else if !is_gamepad && was_connected {
+                log::info!("gamepad {:?} disconnected", id);
+                self.connected.retain(|connected| *connected != id);
+                events.push(GamepadEvent::Disconnected { id });
+            }
+
+            let is_unmapped = !is_gamepad && joystick.is_present();
+            if is_unmapped && !self.unmapped.contains(&id) {
+                log::warn!(
+                    "joystick {:?} ({}) has no gamepad mapping and is ignored",
+                    id,
+                    joystick.get_name().unwrap_or_default()
+                );
+                self.unmapped.push(id);
+            } else if !is_unmapped {
+                self.unmapped.retain(|unmapped| *unmapped != id);
+            }
+        }
+        let active = *self.connected.first()?;
+        glfw.get_joystick(active).get_gamepad_state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close((x, y): (f32, f32), (expected_x, expected_y): (f32, f32)) {
+        assert!(
+            (x - expected_x).abs() < 1e-6 && (y - expected_y).abs() < 1e-6,
+            "({}, {}) is not ({}, {})",
+            x,
+            y,
+            expected_x,
+            expected_y
+        );
+    }
+
+    #[test]
+    fn sticks_are_rescaled_outside_the_dead_zone() {
+        let zones = DeadZones::default();
+        assert_eq!(zones.apply_to_stick(0.15, 0.), (0., 0.));
+        assert_eq!(zones.apply_to_stick(0.1, -0.1), (0., 0.));
+        assert_close(zones.apply_to_stick(0.575, 0.), (0.5, 0.));
+        assert_close(zones.apply_to_stick(0., -1.), (0., -1.));
+        // The corners of a square gate are clamped to the unit circle, without snapping to an
+        // axis.
+        let corner = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(zones.apply_to_stick(1., 1.), (corner, corner));
+        assert_close(zones.apply_to_stick(-0.3, 0.4), (-0.247_058_8, 0.329_411_8));
+    }
+
+    #[test]
+    fn triggers_are_mapped_to_zero_to_one() {
+        let zones = DeadZones::default();
+        assert_eq!(zones.apply_to_trigger(-1.), 0.);
+        assert_eq!(zones.apply_to_trigger(-0.95), 0.);
+        let quarter = DeadZones {
+            stick: 0.25,
+            trigger: 0.25,
+        };
+        assert_eq!(quarter.apply_to_trigger(-0.5), 0.);
+        assert_close((zones.apply_to_trigger(0.), 0.), (0.473_684_2, 0.));
+        assert_close((zones.apply_to_trigger(1.), 0.), (1., 0.));
+    }
+
+    #[test]
+    fn full_dead_zones_leave_a_usable_range() {
+        let zones = DeadZones {
+            stick: 1.,
+            trigger: f32::NAN,
+        };
+        assert_eq!(zones.apply_to_stick(0.9, 0.), (0., 0.));
+        assert_close(zones.apply_to_stick(1., 0.), (1., 0.));
+        assert_eq!(zones.apply_to_trigger(1.), 1.);
+        assert!(!zones.apply_to_trigger(0.5).is_nan());
+    }
+}
@@ -0,0 +1,185 @@
+//! Routes driver messages from `KHR_debug` / `ARB_debug_output` to the logger.
+//!
+//! Most drivers only report errors through the callback when the context was created with the
+//! debug flag, which costs performance, so the debug context is opt-in.
+
+use std::ffi::{c_void, CStr};
+use std::sync::OnceLock;
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+use crate::shader::{gl_version, has_extension};
+
+/// Lowest severity of the driver messages that are logged.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => Severity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => Severity::Medium,
+            gl::DEBUG_SEVERITY_LOW => Severity::Low,
+            _ => Severity::Notification,
+        }
+    }
+
+    fn gl_severity(self) -> GLenum {
+        match self {
+            Severity::High => gl::DEBUG_SEVERITY_HIGH,
+            Severity::Medium => gl::DEBUG_SEVERITY_MEDIUM,
+            Severity::Low => gl::DEBUG_SEVERITY_LOW,
+            Severity::Notification => gl::DEBUG_SEVERITY_NOTIFICATION,
+        }
+    }
+
+    fn log_level(self) -> log::Level {
+        match self {
+            Severity::High => log::Level::Error,
+            Severity::Medium => log::Level::Warn,
+            Severity::Low => log::Level::Info,
+            Severity::Notification => log::Level::Debug,
+        }
+    }
+}
+
+/// How the context reports debug messages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Support {
+    /// OpenGL 4.3 or `KHR_debug`.
+    Khr,
+    /// Only `ARB_debug_output`, which has no `GL_DEBUG_OUTPUT` switch and no object labels.
+    Arb,
+}
+
+/// Checks the version and extensions rather than whether the functions loaded, since GLX hands
+/// out a pointer for any name. The engine creates a single context, so this is asked once.
+fn support() -> Option<Support> {
+    static SUPPORT: OnceLock<Option<Support>> = OnceLock::new();
+    *SUPPORT.get_or_init(|| unsafe {
+        if gl_version() >= (4, 3) || has_extension("GL_KHR_debug") {
+            Some(Support::Khr)
+        } else if has_extension("GL_ARB_debug_output") {
+            Some(Support::Arb)
+        } else {
+            None
+        }
+    })
+}
+
+/// Installs the message callback and mutes the messages below `min_severity`.
+///
+/// Returns `false` if the context has neither OpenGL 4.3, `KHR_debug` nor `ARB_debug_output`.
+/// Messages are delivered synchronously, so the logged message belongs to the GL call that
+/// caused it and a breakpoint in the callback shows the offending call on the stack.
+pub fn enable(min_severity: Severity) -> bool {
+    let support = match support() {
+        Some(support) => support,
+        None => return false,
+    };
+    unsafe {
+        if support == Support::Khr {
+            gl::Enable(gl::DEBUG_OUTPUT);
+        }
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(callback), std::ptr::null());
+        for severity in [
+            Severity::Notification,
+            Severity::Low,
+            Severity::Medium,
+            Severity::High,
+        ] {
+            let enabled = if severity >= min_severity {
+                gl::TRUE
+            } else {
+                gl::FALSE
+            };
+            gl::DebugMessageControl(
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                severity.gl_severity(),
+                0,
+                std::ptr::null(),
+                enabled,
+            );
+        }
+    }
+    true
+}
+
+/// Names a GL object in driver messages and in debuggers like RenderDoc.
+///
+/// `identifier` is the kind of object, such as `gl::TEXTURE` or `gl::BUFFER`. The object has to
+/// exist, so buffers, textures and vertex arrays have to be bound once before they are
+/// labelled. Does nothing without OpenGL 4.3 or `KHR_debug`.
+pub fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if support() != Some(Support::Khr) {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            name,
+            label.len() as GLsizei,
+            label.as_ptr().cast(),
+        );
+    }
+}
+
+extern "system" fn callback(
+    source: GLenum,
+    kind: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe {
+        if length >= 0 {
+            let bytes = std::slice::from_raw_parts(message.cast::<u8>(), length as usize);
+            String::from_utf8_lossy(bytes).into_owned()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    };
+    log::log!(
+        target: "gl",
+        Severity::from_gl(severity).log_level(),
+        "{} {} {}: {}",
+        source_name(source),
+        type_name(kind),
+        id,
+        message.trim_end()
+    );
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
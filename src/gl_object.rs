@@ -0,0 +1,105 @@
+//! Owning wrappers for GL object names.
+//!
+//! Each wrapper deletes its object when dropped. GL objects belong to the context that is
+//! current on the thread that created them, so the wrappers are neither `Send` nor `Sync`, and
+//! the raw name is only handed out through `id()` for the calls the wrappers do not cover.
+
+use std::marker::PhantomData;
+
+use crate::gl_debug;
+
+/// Makes the wrappers `!Send` and `!Sync`.
+type NotSend = PhantomData<*const ()>;
+
+macro_rules! gl_object {
+    ($name:ident, $generate:ident, $delete:ident, $identifier:ident) => {
+        #[derive(Debug)]
+        pub struct $name {
+            id: gl::types::GLuint,
+            _not_send: NotSend,
+        }
+
+        impl $name {
+            // Not `Default`: creating the object needs a current context.
+            #[allow(clippy::new_without_default)]
+            pub fn new() -> Self {
+                let mut id = 0;
+                unsafe {
+                    gl::$generate(1, &mut id);
+                }
+                Self {
+                    id,
+                    _not_send: PhantomData,
+                }
+            }
+
+            /// The raw GL name. It stays owned by `self` and must not be deleted.
+            pub fn id(&self) -> gl::types::GLuint {
+                self.id
+            }
+
+            /// Names the object in driver messages. It has to be bound once before.
+            pub fn label(&self, label: &str) {
+                gl_debug::label(gl::$identifier, self.id, label);
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                unsafe {
+                    gl::$delete(1, &self.id);
+                }
+            }
+        }
+    };
+}
+
+gl_object!(Texture, GenTextures, DeleteTextures, TEXTURE);
+gl_object!(Buffer, GenBuffers, DeleteBuffers, BUFFER);
+gl_object!(
+    VertexArray,
+    GenVertexArrays,
+    DeleteVertexArrays,
+    VERTEX_ARRAY
+);
+gl_object!(
+    Framebuffer,
+    GenFramebuffers,
+    DeleteFramebuffers,
+    FRAMEBUFFER
+);
+
+/// A linked or still empty program object.
+#[derive(Debug)]
+pub struct Program {
+    id: gl::types::GLuint,
+    _not_send: NotSend,
+}
+
+impl Program {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            id: unsafe { gl::CreateProgram() },
+            _not_send: PhantomData,
+        }
+    }
+
+    /// The raw GL name. It stays owned by `self` and must not be deleted.
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    /// Names the program in driver messages.
+    pub fn label(&self, label: &str) {
+        gl_debug::label(gl::PROGRAM, self.id, label);
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
@@ -0,0 +1,718 @@
+//! Validation of GLSL sources without a GL context.
+//!
+//! Every stage is preprocessed like for the driver, then parsed and type-checked by naga's GLSL
+//! front end. On top of that, the outputs of each stage are checked against the inputs of the
+//! next one and uniforms shared by several stages against each other, like the linker would. It
+//! does not replace the driver's compiler, but catches broken edits in CI, where there is no GPU
+//! to compile on.
+//!
+//! naga reads the Vulkan flavour of GLSL 4.50, so the sources are adapted first, keeping every
+//! line where it was: the `#version` is raised, uniforms get the `binding` Vulkan requires, and
+//! the combined samplers OpenGL has and Vulkan does not are declared as stand-ins.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use naga::front::glsl::{Frontend, Options};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{AddressSpace, Binding, Handle, Module, Scalar, ScalarKind, Span, Type, TypeInner};
+
+use crate::preprocessor::{self, Defines};
+use crate::shader::{ShaderError, ShaderStage};
+
+/// Declares the opaque types of OpenGL GLSL that naga has no equivalent for, with the functions
+/// our shaders call on them. They only need to type-check; nothing runs them.
+const STAND_INS: &str = "\
+struct sampler2D { int unused; };
+struct samplerCube { int unused; };
+vec4 texture(sampler2D s, vec2 coord) { return vec4(0.0); }
+vec4 texture(sampler2D s, vec2 coord, float bias) { return vec4(0.0); }
+vec4 texture(samplerCube s, vec3 coord) { return vec4(0.0); }
+vec4 textureLod(sampler2D s, vec2 coord, float lod) { return vec4(0.0); }
+vec4 texelFetch(sampler2D s, ivec2 coord, int lod) { return vec4(0.0); }
+ivec2 textureSize(sampler2D s, int lod) { return ivec2(0); }
+";
+
+#[derive(Debug)]
+pub struct GlslError {
+    pub path: PathBuf,
+    /// 1-based, or 0 for errors about the file as a whole.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for GlslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path.display(), self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for GlslError {}
+
+/// Adapted source of a stage, with the file and line each of its lines came from.
+struct Source {
+    text: String,
+    origins: Vec<(usize, usize)>,
+    files: Vec<PathBuf>,
+}
+
+impl Source {
+    fn error(&self, span: Span, message: String) -> GlslError {
+        let origin = span.to_range().and_then(|range| {
+            let line = self.text[..range.start.min(self.text.len())]
+                .matches('\n')
+                .count();
+            self.origins.get(line)
+        });
+        match origin {
+            Some(&(file, line)) => GlslError {
+                path: self.files.get(file).cloned().unwrap_or_default(),
+                line,
+                message,
+            },
+            None => GlslError {
+                path: self.files.first().cloned().unwrap_or_default(),
+                line: 0,
+                message,
+            },
+        }
+    }
+}
+
+/// A global `in`, `out` or `uniform` declaration. Anonymous interface blocks are recorded
+/// under their block name, which is what the linker matches them by.
+struct Variable {
+    name: String,
+    ty: Handle<Type>,
+    span: Span,
+}
+
+struct CheckedStage {
+    stage: ShaderStage,
+    module: Module,
+    source: Source,
+    inputs: Vec<Variable>,
+    outputs: Vec<Variable>,
+    uniforms: Vec<Variable>,
+}
+
+impl CheckedStage {
+    fn new(stage: ShaderStage, module: Module, source: Source) -> Self {
+        let mut checked = CheckedStage {
+            stage,
+            module,
+            source,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            uniforms: Vec::new(),
+        };
+        let module = &checked.module;
+        // naga passes the stage inputs and outputs of GLSL through the entry point, and keeps the
+        // globals they were declared as, which is where their spans are.
+        let span_of = |name: &str| {
+            module
+                .global_variables
+                .iter()
+                .find(|(_, global)| global.name.as_deref() == Some(name))
+                .map_or(Span::UNDEFINED, |(handle, _)| {
+                    module.global_variables.get_span(handle)
+                })
+        };
+        let varying = |name: &Option<String>, ty, binding: &Option<Binding>| match (name, binding) {
+            (Some(name), Some(Binding::Location { .. })) => Some(Variable {
+                name: name.clone(),
+                ty,
+                span: span_of(name),
+            }),
+            _ => None,
+        };
+        if let Some(entry_point) = module.entry_points.first() {
+            let function = &entry_point.function;
+            checked.inputs = function
+                .arguments
+                .iter()
+                .filter_map(|argument| varying(&argument.name, argument.ty, &argument.binding))
+                .collect();
+            if let Some(result) = &function.result {
+                checked.outputs = match &module.types[result.ty].inner {
+                    TypeInner::Struct { members, .. } => members
+                        .iter()
+                        .filter_map(|member| varying(&member.name, member.ty, &member.binding))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+            }
+        }
+        checked.uniforms = module
+            .global_variables
+            .iter()
+            .filter(|(_, global)| {
+                matches!(global.space, AddressSpace::Uniform | AddressSpace::Handle)
+            })
+            .filter_map(|(handle, global)| {
+                let name = global
+                    .name
+                    .clone()
+                    .or_else(|| module.types[global.ty].name.clone())?;
+                Some(Variable {
+                    name,
+                    ty: global.ty,
+                    span: module.global_variables.get_span(handle),
+                })
+            })
+            .collect();
+        checked
+    }
+
+    fn error(&self, variable: &Variable, message: String) -> GlslError {
+        self.source.error(variable.span, message)
+    }
+}
+
+/// Checks the stages of a program, as given to [`ShaderBuilder`](crate::shader::ShaderBuilder).
+///
+/// Every stage is checked on its own first; the interfaces between stages are only compared
+/// when all of them are valid. naga has no front end for tessellation and geometry shaders, so
+/// those are only preprocessed, and the stages around them are not compared.
+pub fn validate(stages: &[(ShaderStage, String)], defines: &Defines) -> Result<(), Vec<GlslError>> {
+    let mut errors = Vec::new();
+    let mut checked = Vec::new();
+    for (stage, path) in stages {
+        match check_stage(*stage, path, defines) {
+            Ok(stage) => checked.push(stage),
+            Err(stage_errors) => errors.extend(stage_errors),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut order: Vec<usize> = (0..stages.len()).collect();
+    order.sort_by_key(|&index| pipeline_order(stages[index].0));
+    for pair in order.windows(2) {
+        if let (Some(producer), Some(consumer)) = (&checked[pair[0]], &checked[pair[1]]) {
+            match_interfaces(producer, consumer, &mut errors);
+        }
+    }
+    let checked: Vec<&CheckedStage> = checked.iter().flatten().collect();
+    match_uniforms(&checked, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_stage(
+    stage: ShaderStage,
+    path: &str,
+    defines: &Defines,
+) -> Result<Option<CheckedStage>, Vec<GlslError>> {
+    let preprocessed = preprocessor::preprocess(path, defines).map_err(|error| {
+        let error = match error {
+            ShaderError::Io { path, source } => GlslError {
+                path: path.into(),
+                line: 0,
+                message: source.to_string(),
+            },
+            ShaderError::Preprocess {
+                path,
+                line,
+                message,
+            } => GlslError {
+                path: path.into(),
+                line,
+                message,
+            },
+            error => GlslError {
+                path: path.into(),
+                line: 0,
+                message: error.to_string(),
+            },
+        };
+        vec![error]
+    })?;
+    let naga_stage = match stage {
+        ShaderStage::Vertex => naga::ShaderStage::Vertex,
+        ShaderStage::Fragment => naga::ShaderStage::Fragment,
+        ShaderStage::Compute => naga::ShaderStage::Compute,
+        ShaderStage::TessControl | ShaderStage::TessEvaluation | ShaderStage::Geometry => {
+            return Ok(None)
+        }
+    };
+    let source = adapt(&preprocessed.source)
+        .map(|text| Source {
+            origins: line_origins(&text),
+            text,
+            files: preprocessed.files,
+        })
+        .ok_or_else(|| {
+            vec![GlslError {
+                path: path.into(),
+                line: 1,
+                message: "missing #version directive".to_string(),
+            }]
+        })?;
+
+    let module = Frontend::default()
+        .parse(&Options::from(naga_stage), &source.text)
+        .map_err(|errors| {
+            errors
+                .errors
+                .iter()
+                .map(|error| source.error(error.meta, error.kind.to_string()))
+                .collect::<Vec<_>>()
+        })?;
+    // Struct layouts and bindings are Vulkan rules that OpenGL's default uniform block does not
+    // follow, and uniformity is not required of derivatives by OpenGL either.
+    let flags = ValidationFlags::EXPRESSIONS | ValidationFlags::BLOCKS | ValidationFlags::CONSTANTS;
+    if let Err(error) = Validator::new(flags, Capabilities::all()).validate(&module) {
+        let span = error
+            .spans()
+            .last()
+            .map_or(Span::UNDEFINED, |(span, _)| *span);
+        let error = error.into_inner();
+        let mut message = error.to_string();
+        let mut cause = error.source();
+        while let Some(inner) = cause {
+            message.push_str(": ");
+            message.push_str(&inner.to_string());
+            cause = inner.source();
+        }
+        return Err(vec![source.error(span, message)]);
+    }
+    Ok(Some(CheckedStage::new(stage, module, source)))
+}
+
+/// Rewrites preprocessed OpenGL GLSL into the GLSL naga reads, without moving any line of it.
+///
+/// Returns `None` without a `#version` directive.
+fn adapt(source: &str) -> Option<String> {
+    let origins = line_origins(source);
+    let mut adapted = String::new();
+    let mut has_version = false;
+    let mut binding = 0;
+    for (line, origin) in source.lines().zip(origins) {
+        if !has_version && preprocessor::is_version(line) {
+            has_version = true;
+            adapted.push_str("#version 450 core\n");
+            adapted.push_str(STAND_INS);
+            adapted.push_str(&format!("#line {} {}\n", origin.1 + 1, origin.0));
+            continue;
+        }
+        match with_binding(line, binding) {
+            Some(line) => {
+                adapted.push_str(&line);
+                binding += 1;
+            }
+            None => adapted.push_str(line),
+        }
+        adapted.push('\n');
+    }
+    has_version.then_some(adapted)
+}
+
+/// Adds `binding` to a uniform declaration, or returns `None` if `line` does not start one.
+fn with_binding(line: &str, binding: u32) -> Option<String> {
+    let declaration = line.trim_start();
+    if declaration.starts_with("uniform ") {
+        return Some(format!("layout (binding = {}) {}", binding, declaration));
+    }
+    let qualifiers = declaration
+        .strip_prefix("layout")?
+        .trim_start()
+        .strip_prefix('(')?;
+    let (qualifiers, rest) = qualifiers.split_once(')')?;
+    rest.trim_start().starts_with("uniform ").then(|| {
+        format!(
+            "layout ({}, binding = {}){}",
+            qualifiers.trim(),
+            binding,
+            rest
+        )
+    })
+}
+
+/// The file and line every line of `source` comes from, following its `#line` directives.
+fn line_origins(source: &str) -> Vec<(usize, usize)> {
+    let mut next = (0, 1);
+    source
+        .lines()
+        .map(|line| {
+            let origin = next;
+            next = line_directive(line, origin.0).unwrap_or((origin.0, origin.1 + 1));
+            origin
+        })
+        .collect()
+}
+
+/// Parses `#line line [file]` into the file and line of the next line.
+fn line_directive(line: &str, file: usize) -> Option<(usize, usize)> {
+    let mut arguments = line
+        .trim_start()
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("line")?
+        .split_whitespace();
+    let line = arguments.next()?.parse().ok()?;
+    let file = match arguments.next() {
+        Some(file) => file.parse().ok()?,
+        None => file,
+    };
+    Some((file, line))
+}
+
+fn pipeline_order(stage: ShaderStage) -> usize {
+    match stage {
+        ShaderStage::Vertex => 0,
+        ShaderStage::TessControl => 1,
+        ShaderStage::TessEvaluation => 2,
+        ShaderStage::Geometry => 3,
+        ShaderStage::Fragment => 4,
+        ShaderStage::Compute => 5,
+    }
+}
+
+/// Checks that every input of `consumer` is written by `producer` with the same type.
+///
+/// Inputs are matched by name, like the linker does for GLSL 3.30, which has no locations on
+/// the outputs of a vertex shader or the inputs of a fragment shader.
+fn match_interfaces(producer: &CheckedStage, consumer: &CheckedStage, errors: &mut Vec<GlslError>) {
+    for input in &consumer.inputs {
+        let output = match producer
+            .outputs
+            .iter()
+            .find(|output| output.name == input.name)
+        {
+            Some(output) => output,
+            None => {
+                let message = format!(
+                    "{} shader input `{}` is not written by the {} shader",
+                    consumer.stage, input.name, producer.stage
+                );
+                errors.push(consumer.error(input, message));
+                continue;
+            }
+        };
+        if let Some(message) =
+            type_mismatch(&input.name, (consumer, input.ty), (producer, output.ty))
+        {
+            errors.push(consumer.error(input, message));
+        }
+    }
+}
+
+/// Checks that uniforms with the same name have the same type in every stage.
+fn match_uniforms(checked: &[&CheckedStage], errors: &mut Vec<GlslError>) {
+    let mut seen: HashMap<&str, (&CheckedStage, &Variable)> = HashMap::new();
+    for stage in checked {
+        for uniform in &stage.uniforms {
+            match seen.get(uniform.name.as_str()) {
+                Some((first, first_uniform)) => {
+                    if let Some(message) = type_mismatch(
+                        &uniform.name,
+                        (stage, uniform.ty),
+                        (first, first_uniform.ty),
+                    ) {
+                        errors.push(stage.error(uniform, message));
+                    }
+                }
+                None => {
+                    seen.insert(&uniform.name, (stage, uniform));
+                }
+            }
+        }
+    }
+}
+
+fn type_mismatch(
+    name: &str,
+    a: (&CheckedStage, Handle<Type>),
+    b: (&CheckedStage, Handle<Type>),
+) -> Option<String> {
+    let (a_module, b_module) = (&a.0.module, &b.0.module);
+    if same_type(a_module, a.1, b_module, b.1) {
+        return None;
+    }
+    let (a_type, b_type) = (type_name(a_module, a.1), type_name(b_module, b.1));
+    if a_type == b_type {
+        Some(format!(
+            "`{}` has a different definition of {} in the {} shader than in the {} shader",
+            name, a_type, a.0.stage, b.0.stage
+        ))
+    } else {
+        Some(format!(
+            "`{}` is {} in the {} shader but {} in the {} shader",
+            name, a_type, a.0.stage, b_type, b.0.stage
+        ))
+    }
+}
+
+/// Compares types across stages, where structs and blocks only match if their members do.
+fn same_type(a_module: &Module, a: Handle<Type>, b_module: &Module, b: Handle<Type>) -> bool {
+    let (a_type, b_type) = (&a_module.types[a], &b_module.types[b]);
+    match (&a_type.inner, &b_type.inner) {
+        (
+            TypeInner::Struct {
+                members: a_members, ..
+            },
+            TypeInner::Struct {
+                members: b_members, ..
+            },
+        ) => {
+            a_type.name == b_type.name
+                && a_members.len() == b_members.len()
+                && a_members
+                    .iter()
+                    .zip(b_members)
+                    .all(|(a, b)| a.name == b.name && same_type(a_module, a.ty, b_module, b.ty))
+        }
+        (
+            TypeInner::Array {
+                base: a_base,
+                size: a_size,
+                ..
+            },
+            TypeInner::Array {
+                base: b_base,
+                size: b_size,
+                ..
+            },
+        ) => a_size == b_size && same_type(a_module, *a_base, b_module, *b_base),
+        (a, b) => a == b,
+    }
+}
+
+/// Spells a type the way GLSL does, for error messages.
+fn type_name(module: &Module, ty: Handle<Type>) -> String {
+    let prefix = |scalar: Scalar| match scalar.kind {
+        ScalarKind::Sint => "i",
+        ScalarKind::Uint => "u",
+        ScalarKind::Bool => "b",
+        ScalarKind::Float if scalar.width == 8 => "d",
+        _ => "",
+    };
+    let ty = &module.types[ty];
+    match &ty.inner {
+        TypeInner::Scalar(scalar) => match (scalar.kind, scalar.width) {
+            (ScalarKind::Sint, _) => "int",
+            (ScalarKind::Uint, _) => "uint",
+            (ScalarKind::Bool, _) => "bool",
+            (_, 8) => "double",
+            _ => "float",
+        }
+        .to_string(),
+        TypeInner::Vector { size, scalar } => format!("{}vec{}", prefix(*scalar), *size as u8),
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } if columns == rows => format!("{}mat{}", prefix(*scalar), *columns as u8),
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => format!("{}mat{}x{}", prefix(*scalar), *columns as u8, *rows as u8),
+        TypeInner::Array { base, size, .. } => match size {
+            naga::ArraySize::Constant(size) => format!("{}[{}]", type_name(module, *base), size),
+            _ => format!("{}[]", type_name(module, *base)),
+        },
+        _ => ty
+            .name
+            .clone()
+            .unwrap_or_else(|| "an opaque type".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::test_support::write_files;
+
+    fn shader(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("shader")
+            .join(name)
+            .display()
+            .to_string()
+    }
+
+    fn validate_files(dir: &Path, vertex: &str, fragment: &str) -> Result<(), Vec<GlslError>> {
+        let stages = [
+            (ShaderStage::Vertex, dir.join(vertex).display().to_string()),
+            (
+                ShaderStage::Fragment,
+                dir.join(fragment).display().to_string(),
+            ),
+        ];
+        validate(&stages, &Defines::new())
+    }
+
+    const VERTEX: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+
+out vec3 FragPos;
+out vec3 Normal;
+
+uniform mat4 model;
+
+void main()
+{
+    FragPos = vec3(model * vec4(aPos, 1.0));
+    Normal = mat3(model) * aNormal;
+    gl_Position = vec4(FragPos, 1.0);
+}
+";
+
+    #[test]
+    fn repository_shaders_are_valid() {
+        let object = [
+            (ShaderStage::Vertex, shader("object_vertex_shader.vs")),
+            (ShaderStage::Fragment, shader("object_fragment_shader.fs")),
+        ];
+        let light = [
+            (ShaderStage::Vertex, shader("light_vertex_shader.vs")),
+            (ShaderStage::Fragment, shader("light_fragment_shader.fs")),
+        ];
+        for (stages, defines) in [
+            (&object, Defines::new()),
+            (&object, Defines::new().with("SPOT_LIGHT")),
+            (&light, Defines::new()),
+        ] {
+            if let Err(errors) = validate(stages, &defines) {
+                let errors: Vec<String> = errors.iter().map(GlslError::to_string).collect();
+                panic!("{}", errors.join("\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn mismatched_interface_is_reported_at_the_input() {
+        let fragment = "#version 330 core
+out vec4 FragColor;
+
+in vec3 FragPos;
+in vec4 Normal;
+in vec2 TexCoords;
+
+void main()
+{
+    FragColor = vec4(FragPos, 1.0) + Normal + vec4(TexCoords, 0.0, 0.0);
+}
+";
+        let dir = write_files(
+            "glsl-interface",
+            &[("shader.vs", VERTEX), ("shader.fs", fragment)],
+        );
+        let errors = validate_files(&dir, "shader.vs", "shader.fs").unwrap_err();
+        let messages: Vec<String> = errors.iter().map(GlslError::to_string).collect();
+        let path = dir.join("shader.fs").display().to_string();
+        assert_eq!(
+            messages,
+            [
+                format!(
+                    "{}:5: `Normal` is vec4 in the fragment shader but vec3 in the vertex shader",
+                    path
+                ),
+                format!(
+                    "{}:6: fragment shader input `TexCoords` is not written by the vertex shader",
+                    path
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_errors_are_reported_with_their_line() {
+        let fragment = "#version 330 core
+out vec4 FragColor;
+
+in vec3 FragPos;
+
+void main()
+{
+    vec3 color = FragPos.xyzw;
+    FragColor = vec4(color, 1.0);
+}
+";
+        let dir = write_files(
+            "glsl-type",
+            &[("shader.vs", VERTEX), ("shader.fs", fragment)],
+        );
+        let errors = validate_files(&dir, "shader.vs", "shader.fs").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, dir.join("shader.fs"));
+        assert_eq!(errors[0].line, 8);
+        assert_eq!(errors[0].message, "Invalid swizzle for vector \"xyzw\"");
+    }
+
+    #[test]
+    fn errors_in_includes_point_at_the_included_file() {
+        let fragment = "#version 330 core
+out vec4 FragColor;
+
+#include \"color.glsl\"
+
+void main()
+{
+    FragColor = color();
+}
+";
+        let include = "vec4 color()
+{
+    return vec3(1.0);
+}
+";
+        let dir = write_files(
+            "glsl-include",
+            &[
+                ("shader.vs", VERTEX),
+                ("shader.fs", fragment),
+                ("color.glsl", include),
+            ],
+        );
+        let errors = validate_files(&dir, "shader.vs", "shader.fs").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, dir.join("color.glsl"));
+        assert_eq!(errors[0].line, 3);
+        assert!(
+            errors[0]
+                .message
+                .contains("does not match the declared return type"),
+            "{}",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn writing_to_inputs_is_an_error() {
+        let fragment = "#version 330 core
+out vec4 FragColor;
+
+in vec3 FragPos;
+uniform vec3 color;
+
+void main()
+{
+    FragPos = color;
+    FragColor = vec4(FragPos, 1.0);
+}
+";
+        let dir = write_files(
+            "glsl-readonly",
+            &[("shader.vs", VERTEX), ("shader.fs", fragment)],
+        );
+        let errors = validate_files(&dir, "shader.vs", "shader.fs").unwrap_err();
+        assert_eq!(errors[0].line, 9);
+        assert_eq!(errors[0].message, "Variable cannot be used in LHS position");
+    }
+}
@@ -0,0 +1,504 @@
+//! Named actions and axes bound to device buttons, loaded from a TOML file.
+//!
+//! ```toml
+//! [actions]
+//! jump = ["key:Space", "gamepad:A"]
+//!
+//! [axes]
+//! move_right = [
+//!     { negative = "key:A", positive = "key:D" },
+//!     { axis = "gamepad:LeftX" },
+//! ]
+//! ```
+//!
+//! Keys use the names of [`glfw::Key`], like `W`, `Num1`, `LeftShift` or `F5`. Mouse buttons
+//! are `mouse:Left`, `mouse:Right`, `mouse:Middle` and `mouse:4` to `mouse:8`. Gamepad buttons
+//! and axes use the names of [`glfw::GamepadButton`] and [`glfw::GamepadAxis`] without the
+//! prefix, like `gamepad:A`, `gamepad:DpadUp` or `gamepad:RightTrigger`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use glfw::{GamepadAxis, GamepadButton, Key, MouseButton};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{ButtonState, Input};
+
+macro_rules! names {
+    ($ty:ident: $($name:ident),* $(,)?) => {
+        &[$((stringify!($name), $ty::$name)),*]
+    };
+    ($ty:ident: $($name:literal => $variant:ident),* $(,)?) => {
+        &[$(($name, $ty::$variant)),*]
+    };
+}
+
+/// A button on one of the devices.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(Key),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButton),
+}
+
+impl Binding {
+    pub fn state(&self, input: &Input) -> ButtonState {
+        match *self {
+            Binding::Key(key) => input.key(key),
+            Binding::MouseButton(button) => input.mouse_button(button),
+            Binding::GamepadButton(button) => input.gamepad_button(button),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{}", name_of(KEYS, key)),
+            Binding::MouseButton(button) => write!(f, "mouse:{}", name_of(MOUSE_BUTTONS, button)),
+            Binding::GamepadButton(button) => {
+                write!(f, "gamepad:{}", name_of(GAMEPAD_BUTTONS, button))
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (device, name) = s.split_once(':').ok_or_else(|| {
+            format!(
+                "`{}` should look like `key:W`, `mouse:Left` or `gamepad:A`",
+                s
+            )
+        })?;
+        let binding = match device {
+            "key" => find(KEYS, name).map(Binding::Key),
+            "mouse" => find(MOUSE_BUTTONS, name).map(Binding::MouseButton),
+            "gamepad" => find(GAMEPAD_BUTTONS, name).map(Binding::GamepadButton),
+            _ => return Err(format!("unknown device `{}` in `{}`", device, s)),
+        };
+        binding.ok_or_else(|| format!("unknown button `{}`", s))
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Something that yields a value in `[-1, 1]`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged, try_from = "AxisBindingFields")]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is held, 0 for both or none.
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    Gamepad {
+        #[serde(with = "gamepad_axis")]
+        axis: GamepadAxis,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        invert: bool,
+    },
+}
+
+impl AxisBinding {
+    pub fn value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => {
+                let pressed = |binding: Binding| binding.state(input).ended_down as i32 as f32;
+                pressed(positive) - pressed(negative)
+            }
+            AxisBinding::Gamepad { axis, invert } => {
+                let value = input.gamepad_axis(axis);
+                if invert {
+                    -value
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+/// The fields of all kinds of [`AxisBinding`], so a mistake in one of them is reported instead
+/// of the table matching no kind at all.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisBindingFields {
+    negative: Option<Binding>,
+    positive: Option<Binding>,
+    #[serde(default, deserialize_with = "gamepad_axis::deserialize_some")]
+    axis: Option<GamepadAxis>,
+    #[serde(default)]
+    invert: bool,
+}
+
+impl TryFrom<AxisBindingFields> for AxisBinding {
+    type Error = &'static str;
+
+    fn try_from(fields: AxisBindingFields) -> Result<Self, Self::Error> {
+        match fields {
+            AxisBindingFields {
+                negative: Some(negative),
+                positive: Some(positive),
+                axis: None,
+                invert: false,
+            } => Ok(AxisBinding::Buttons { negative, positive }),
+            AxisBindingFields {
+                negative: None,
+                positive: None,
+                axis: Some(axis),
+                invert,
+            } => Ok(AxisBinding::Gamepad { axis, invert }),
+            _ => Err("an axis binding needs either `negative` and `positive`, or `axis`"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputMapError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMapError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            InputMapError::Parse(path, err) => write!(f, "{}: {}", path.display(), err),
+            InputMapError::Serialize(err) => write!(f, "cannot serialize bindings: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InputMapError {}
+
+/// Maps action and axis names to the inputs bound to them.
+///
+/// An action is held while any of its buttons is, and axes add up their bindings. Bindings
+/// can be changed at any time, the next query sees them.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct InputMap {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Binding>>,
+    #[serde(default)]
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputMapError> {
+        let path = path.as_ref();
+        let source =
+            fs::read_to_string(path).map_err(|err| InputMapError::Io(path.to_owned(), err))?;
+        Self::from_toml(&source).map_err(|err| InputMapError::Parse(path.to_owned(), err))
+    }
+
+    /// Parses bindings in the format of the files [`load`](Self::load) reads.
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputMapError> {
+        let path = path.as_ref();
+        let source = toml::to_string_pretty(self).map_err(InputMapError::Serialize)?;
+        fs::write(path, source).map_err(|err| InputMapError::Io(path.to_owned(), err))
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: &AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|bound| bound != binding);
+        }
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Removes every binding of an action or axis.
+    pub fn clear(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    /// The combined state of the buttons bound to `action`, which is down while any of them is.
+    /// Unknown actions are never down.
+    ///
+    /// Pressing and releasing one button while another keeps the action down is not a
+    /// transition of the action.
+    pub fn action(&self, input: &Input, action: &str) -> ButtonState {
+        let states: Vec<ButtonState> = self
+            .bindings(action)
+            .iter()
+            .map(|binding| binding.state(input))
+            .collect();
+        let started_down = states.iter().any(ButtonState::started_down);
+        let ended_down = states.iter().any(ButtonState::is_held);
+        let half_transition_count = if started_down != ended_down {
+            1
+        } else if !ended_down && states.iter().any(|state| state.half_transition_count > 1) {
+            2
+        } else {
+            0
+        };
+        ButtonState {
+            half_transition_count,
+            ended_down,
+            held_time: states
+                .iter()
+                .filter(|state| state.ended_down)
+                .map(|state| state.held_time)
+                .fold(0., f32::max),
+        }
+    }
+
+    /// The sum of the bindings of `axis`, clamped to `[-1, 1]`. Unknown axes are 0.
+    pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+        self.axis_bindings(axis)
+            .iter()
+            .map(|binding| binding.value(input))
+            .sum::<f32>()
+            .clamp(-1., 1.)
+    }
+}
+
+fn find<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+        .map(|(_, value)| *value)
+}
+
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> &'static str {
+    names
+        .iter()
+        .find(|(_, candidate)| candidate == value)
+        .map_or("Unknown", |(name, _)| name)
+}
+
+mod gamepad_axis {
+    use glfw::GamepadAxis;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(axis: &GamepadAxis, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!(
+            "gamepad:{}",
+            super::name_of(super::GAMEPAD_AXES, axis)
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<GamepadAxis, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.strip_prefix("gamepad:")
+            .and_then(|axis| super::find(super::GAMEPAD_AXES, axis))
+            .ok_or_else(|| de::Error::custom(format!("unknown gamepad axis `{}`", name)))
+    }
+
+    pub fn deserialize_some<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<GamepadAxis>, D::Error> {
+        deserialize(deserializer).map(Some)
+    }
+}
+
+const KEYS: &[(&str, Key)] = names!(Key:
+    Space, Apostrophe, Comma, Minus, Period, Slash, Num0, Num1, Num2, Num3, Num4, Num5, Num6,
+    Num7, Num8, Num9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S,
+    T, U, V, W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2,
+    Escape, Enter, Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown, Home,
+    End, CapsLock, ScrollLock, NumLock, PrintScreen, Pause, F1, F2, F3, F4, F5, F6, F7, F8, F9,
+    F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, Kp0, Kp1,
+    Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9, KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd,
+    KpEnter, KpEqual, LeftShift, LeftControl, LeftAlt, LeftSuper, RightShift, RightControl,
+    RightAlt, RightSuper, Menu,
+);
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = names!(MouseButton:
+    "Left" => Button1,
+    "Right" => Button2,
+    "Middle" => Button3,
+    "4" => Button4,
+    "5" => Button5,
+    "6" => Button6,
+    "7" => Button7,
+    "8" => Button8,
+);
+
+const GAMEPAD_BUTTONS: &[(&str, GamepadButton)] = names!(GamepadButton:
+    "A" => ButtonA,
+    "B" => ButtonB,
+    "X" => ButtonX,
+    "Y" => ButtonY,
+    "LeftBumper" => ButtonLeftBumper,
+    "RightBumper" => ButtonRightBumper,
+    "Back" => ButtonBack,
+    "Start" => ButtonStart,
+    "Guide" => ButtonGuide,
+    "LeftThumb" => ButtonLeftThumb,
+    "RightThumb" => ButtonRightThumb,
+    "DpadUp" => ButtonDpadUp,
+    "DpadRight" => ButtonDpadRight,
+    "DpadDown" => ButtonDpadDown,
+    "DpadLeft" => ButtonDpadLeft,
+);
+
+const GAMEPAD_AXES: &[(&str, GamepadAxis)] = names!(GamepadAxis:
+    "LeftX" => AxisLeftX,
+    "LeftY" => AxisLeftY,
+    "RightX" => AxisRightX,
+    "RightY" => AxisRightY,
+    "LeftTrigger" => AxisLeftTrigger,
+    "RightTrigger" => AxisRightTrigger,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<InputMap, String> {
+        toml::from_str(source).map_err(|err| err.message().to_string())
+    }
+
+    #[test]
+    fn actions_and_axes_are_parsed() {
+        let map = parse(
+            r#"
+            [actions]
+            jump = ["key:Space", "mouse:Left", "gamepad:A"]
+
+            [axes]
+            move_right = [
+                { negative = "key:A", positive = "key:D" },
+                { axis = "gamepad:LeftX" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.bindings("jump"),
+            [
+                Binding::Key(Key::Space),
+                Binding::MouseButton(MouseButton::Button1),
+                Binding::GamepadButton(GamepadButton::ButtonA),
+            ]
+        );
+        assert_eq!(
+            map.axis_bindings("move_right"),
+            [
+                AxisBinding::Buttons {
+                    negative: Binding::Key(Key::A),
+                    positive: Binding::Key(Key::D),
+                },
+                AxisBinding::Gamepad {
+                    axis: GamepadAxis::AxisLeftX,
+                    invert: false,
+                },
+            ]
+        );
+        assert_eq!(parse(&toml::to_string_pretty(&map).unwrap()).unwrap(), map);
+    }
+
+    #[test]
+    fn unknown_devices_and_names_are_reported() {
+        let cases = [
+            (
+                r#"actions = { jump = ["pad:A"] }"#,
+                "unknown device `pad` in `pad:A`",
+            ),
+            (
+                r#"actions = { jump = ["key:Jump"] }"#,
+                "unknown button `key:Jump`",
+            ),
+            (
+                r#"actions = { jump = ["Space"] }"#,
+                "`Space` should look like `key:W`, `mouse:Left` or `gamepad:A`",
+            ),
+            (
+                r#"axes = { look = [{ axis = "gamepad:Up" }] }"#,
+                "unknown gamepad axis `gamepad:Up`",
+            ),
+            (
+                r#"axes = { look = [{ negative = "key:A", axis = "gamepad:LeftX" }] }"#,
+                "an axis binding needs either `negative` and `positive`, or `axis`",
+            ),
+        ];
+        for (source, message) in cases {
+            assert_eq!(parse(source).unwrap_err(), message, "for {}", source);
+        }
+    }
+
+    #[test]
+    fn repository_bindings_are_valid() {
+        let map = InputMap::from_toml(include_str!("../input.toml")).unwrap();
+        assert!(!map.bindings("toggle_flashlight").is_empty());
+        assert!(!map.axis_bindings("move_forward").is_empty());
+    }
+
+    #[test]
+    fn inverted_axes_flip_the_gamepad_value() {
+        let map =
+            parse(r#"axes = { look_up = [{ axis = "gamepad:RightY", invert = true }] }"#).unwrap();
+        assert_eq!(
+            map.axis_bindings("look_up"),
+            [AxisBinding::Gamepad {
+                axis: GamepadAxis::AxisRightY,
+                invert: true,
+            }]
+        );
+        let mut input = Input::new(0., 0.);
+        input.gamepad_axes[GamepadAxis::AxisRightY as usize] = -0.5;
+        assert_eq!(map.axis(&input, "look_up"), 0.5);
+
+        let buttons =
+            r#"axes = { look_up = [{ negative = "key:S", positive = "key:W", invert = true }] }"#;
+        assert!(parse(buttons).is_err());
+    }
+}
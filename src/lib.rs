@@ -0,0 +1,23 @@
+extern crate nalgebra_glm as glm;
+
+pub mod camera;
+pub mod engine;
+pub mod frame_pacer;
+pub mod gamepad;
+pub mod gl_debug;
+pub mod gl_object;
+pub mod glsl;
+pub mod input_map;
+pub mod lighting;
+pub mod preprocessor;
+pub mod program_cache;
+pub mod reflection;
+pub mod replay;
+pub mod shader;
+pub mod shader_source;
+#[cfg(test)]
+mod test_support;
+pub mod uniform;
+pub mod uniform_buffer;
+pub mod utils;
+pub mod window_mode;
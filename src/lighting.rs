@@ -0,0 +1,36 @@
+use crate::uniform_struct;
+
+uniform_struct! {
+    /// Mirrors `struct Material` in `shader/material.glsl`.
+    #[derive(Clone, Debug)]
+    pub struct Material {
+        /// Texture unit of the diffuse map.
+        pub diffuse: i32,
+        /// Texture unit of the specular map.
+        pub specular: i32,
+        pub shininess: f32,
+    }
+}
+
+uniform_struct! {
+    /// Mirrors `struct Light` in `shader/light.glsl`.
+    #[derive(Clone, Debug)]
+    pub struct SpotLight {
+        pub position: glm::Vec3,
+        pub direction: glm::Vec3,
+        /// Cosine of the inner cone angle.
+        #[glsl = "cutOff"]
+        pub cut_off: f32,
+        /// Cosine of the outer cone angle.
+        #[glsl = "outerCutOff"]
+        pub outer_cut_off: f32,
+
+        pub ambient: glm::Vec3,
+        pub diffuse: glm::Vec3,
+        pub specular: glm::Vec3,
+
+        pub constant: f32,
+        pub linear: f32,
+        pub quadratic: f32,
+    }
+}
@@ -1,299 +1,352 @@
 extern crate nalgebra_glm as glm;
 
-use camera::Camera;
-use glfw::{Action, Context, Key, MouseButton};
-use shader::Shader;
+use std::path::{Path, PathBuf};
+
+use glfw::{Action, Key, Window, WindowEvent};
+use graphic_engine::camera::{Camera, CameraBlock};
+use graphic_engine::engine::{Application, Engine, EngineConfig, Surface};
+use graphic_engine::gl_object::{Buffer, Texture, VertexArray};
+use graphic_engine::input_map::InputMap;
+use graphic_engine::lighting::{Material, SpotLight};
+use graphic_engine::preprocessor::Defines;
+use graphic_engine::program_cache::ProgramCache;
+use graphic_engine::reflection;
+use graphic_engine::shader::{ReloadableShader, Shader, ShaderBuilder};
+use graphic_engine::uniform_buffer::UniformBuffer;
+use graphic_engine::utils::Input;
 use stb_image::image::LoadResult;
-use utils::Input;
 
-mod camera;
-mod shader;
-mod utils;
+/// Attribute locations and types set up by `create_vao`.
+const OBJECT_VERTEX_LAYOUT: [(gl::types::GLint, gl::types::GLenum); 3] = [
+    (0, gl::FLOAT_VEC3),
+    (1, gl::FLOAT_VEC3),
+    (2, gl::FLOAT_VEC2),
+];
+const LIGHT_VERTEX_LAYOUT: [(gl::types::GLint, gl::types::GLenum); 1] = [(0, gl::FLOAT_VEC3)];
 
-const FPS_CAP: f32 = (1.0 / 60.0) * 1000.0;
+/// Uniform buffer binding point of the `Camera` block.
+const CAMERA_BINDING: gl::types::GLuint = 0;
 
-fn main() {
-    let mut camera = Camera {
-        position: glm::Vec3::new(0., 0., 3.),
-        ..Camera::default()
-    };
+/// The bindings of `input.toml` as they were at build time, for when the file cannot be read.
+const DEFAULT_BINDINGS: &str = include_str!("../input.toml");
 
-    let width = 1400;
-    let height = 900;
+/// Degrees per second the camera turns with a stick pushed all the way.
+const STICK_LOOK_SPEED: f32 = 150.;
+/// Degrees of field of view per second a fully pulled trigger zooms by.
+const TRIGGER_ZOOM_SPEED: f32 = 30.;
 
-    let mut running = true;
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let config = EngineConfig {
+        // Debug contexts make drivers report far more, but are slower, so they are opt-in.
+        debug_context: std::env::var_os("GL_DEBUG").is_some(),
+        record: std::env::var_os("GL_RECORD").map(PathBuf::from),
+        replay: std::env::var_os("GL_REPLAY").map(PathBuf::from),
+        ..EngineConfig::default()
+    };
+    Engine::new(config)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .run::<Demo>();
+}
 
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
-    glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
-    glfw.window_hint(glfw::WindowHint::OpenGlProfile(
-        glfw::OpenGlProfileHint::Core,
-    ));
+/// Textured cubes lit by a flashlight, with a small cube orbiting as a light marker.
+struct Demo {
+    bindings: InputMap,
+    camera: Camera,
+    aspect_ratio: f32,
+    time: f32,
+    /// Camera position and time before the last tick, blended with the current ones when
+    /// rendering.
+    previous_position: glm::Vec3,
+    previous_time: f32,
+
+    object_shader: ReloadableShader,
+    light_shader: ReloadableShader,
+    camera_block: CameraBlock,
+    camera_buffer: UniformBuffer<CameraBlock>,
+    material: Material,
+    flashlight: SpotLight,
+    flashlight_on: bool,
+    light_paused: bool,
+
+    cube_positions: Vec<glm::Vec3>,
+    _cube_vertices: Buffer,
+    object_vao: VertexArray,
+    light_vao: VertexArray,
+    diffuse_texture: Texture,
+    specular_texture: Texture,
+}
 
-    let (mut window, events) = glfw
-        .create_window(width, height, "GL Engine", glfw::WindowMode::Windowed)
-        .expect("Window creation failed");
+impl Application for Demo {
+    fn init(window: &mut Window) -> Self {
+        window.set_cursor_mode(glfw::CursorMode::Disabled);
 
-    window.make_current();
-    window.set_framebuffer_size_polling(true);
-    window.set_cursor_pos_polling(true);
-    window.set_mouse_button_polling(true);
-    window.set_scroll_polling(true);
-    window.set_key_polling(true);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+        }
 
-    window.set_cursor_mode(glfw::CursorMode::Disabled);
+        let camera = Camera {
+            position: glm::Vec3::new(0., 0., 3.),
+            ..Camera::default()
+        };
 
-    gl::load_with(|symbol| window.get_proc_address(symbol));
+        let program_cache = ProgramCache::new(std::env::temp_dir().join("graphic_engine_programs"));
 
-    unsafe {
-        gl::Enable(gl::DEPTH_TEST);
-        gl::DepthFunc(gl::LESS);
-    }
+        let object_shader = ShaderBuilder::new()
+            .vertex("shader/object_vertex_shader.vs")
+            .fragment("shader/object_fragment_shader.fs")
+            .defines(Defines::new().with("SPOT_LIGHT"))
+            .cache(program_cache.clone())
+            .build_reloadable()
+            .unwrap_or_else(|err| panic!("{}", err));
 
-    let object_shader = Shader::new(
-        "shader/object_vertex_shader.vs",
-        "shader/object_fragment_shader.fs",
-    );
+        let light_shader = ShaderBuilder::new()
+            .vertex("shader/light_vertex_shader.vs")
+            .fragment("shader/light_fragment_shader.fs")
+            .cache(program_cache)
+            .build_reloadable()
+            .unwrap_or_else(|err| panic!("{}", err));
 
-    let light_shader = Shader::new(
-        "shader/light_vertex_shader.vs",
-        "shader/light_fragment_shader.fs",
-    );
+        let (cube_vertices, object_vao, light_vao) = create_vao();
+        check_shader_interface(&object_shader, &OBJECT_VERTEX_LAYOUT);
+        check_shader_interface(&light_shader, &LIGHT_VERTEX_LAYOUT);
 
-    let (object_vao, light_vao) = create_vao();
+        let diffuse_texture = create_texture("images/container2.png");
 
-    let diffuse_texture = create_texture("images/container2.png");
+        let specular_texture = create_texture("images/container2_specular.png");
 
-    let specular_texture = create_texture("images/container2_specular.png");
+        let material = Material {
+            diffuse: 0,
+            specular: 1,
+            shininess: 64.,
+        };
+        let flashlight = SpotLight {
+            position: camera.position,
+            direction: camera.front,
+            cut_off: 12.5_f32.to_radians().cos(),
+            outer_cut_off: 17.5_f32.to_radians().cos(),
+            ambient: glm::vec3(0.2, 0.2, 0.2),
+            diffuse: glm::vec3(0.5, 0.5, 0.5),
+            specular: glm::vec3(1.0, 1.0, 1.0),
+            constant: 1.,
+            linear: 0.09,
+            quadratic: 0.032,
+        };
 
-    let mut old_input = Input::new(width as f32 / 2., height as f32 / 2.);
-    let mut new_input;
+        let camera_block = CameraBlock {
+            view: camera.view_matrix(),
+            projection: glm::Mat4::identity(),
+            position: camera.position,
+        };
+        let camera_buffer = UniformBuffer::new(CAMERA_BINDING, &camera_block);
+        camera_buffer.attach(&object_shader);
+        camera_buffer.attach(&light_shader);
+
+        set_object_constants(&object_shader, &material);
+
+        let cube_positions = vec![
+            glm::vec3(0., 0., 0.),       //
+            glm::vec3(2., 5., -15.0),    //
+            glm::vec3(-1.5, -2.2, -2.5), //
+            glm::vec3(-3.8, -2., -12.3), //
+            glm::vec3(2.4, -0.4, -3.5),  //
+            glm::vec3(-1.7, 3., -7.5),   //
+            glm::vec3(1.3, -2., -2.5),   //
+            glm::vec3(1.5, 2., -2.5),    //
+            glm::vec3(1.5, 0.2, -1.5),   //
+            glm::vec3(-1.3, 1., -1.5),   //
+        ];
+
+        // Read from the crate root so edits apply without rebuilding, wherever the demo is started.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("input.toml");
+        let bindings = InputMap::load(&path).unwrap_or_else(|err| {
+            log::warn!("{}, using the built-in bindings", err);
+            InputMap::from_toml(DEFAULT_BINDINGS).expect("the built-in bindings are valid")
+        });
+
+        Self {
+            bindings,
+            previous_position: camera.position,
+            camera,
+            aspect_ratio: Surface::from_window(window).aspect_ratio(),
+            time: 0.,
+            previous_time: 0.,
+            object_shader,
+            light_shader,
+            camera_block,
+            camera_buffer,
+            material,
+            flashlight,
+            flashlight_on: true,
+            light_paused: false,
+            cube_positions,
+            _cube_vertices: cube_vertices,
+            object_vao,
+            light_vao,
+            diffuse_texture,
+            specular_texture,
+        }
+    }
 
-    object_shader.use_shader();
-    object_shader.set_i32("material.diffuse", 0);
-    object_shader.set_i32("material.specular", 1);
-    object_shader.set_f32("material.shininess", 64.);
-    object_shader.set_3_f32("light.ambient", 0.2, 0.2, 0.2);
-    object_shader.set_3_f32("light.diffuse", 0.5, 0.5, 0.5);
-    object_shader.set_3_f32("light.specular", 1.0, 1.0, 1.0);
-
-    let cube_positions: Vec<glm::Vec3> = vec![
-        glm::vec3(0., 0., 0.),       //
-        glm::vec3(2., 5., -15.0),    //
-        glm::vec3(-1.5, -2.2, -2.5), //
-        glm::vec3(-3.8, -2., -12.3), //
-        glm::vec3(2.4, -0.4, -3.5),  //
-        glm::vec3(-1.7, 3., -7.5),   //
-        glm::vec3(1.3, -2., -2.5),   //
-        glm::vec3(1.5, 2., -2.5),    //
-        glm::vec3(1.5, 0.2, -1.5),   //
-        glm::vec3(-1.3, 1., -1.5),   //
-    ];
+    fn resize(&mut self, surface: &Surface) {
+        self.aspect_ratio = surface.aspect_ratio();
+    }
 
-    let mut delta: f32;
-    let mut last_frame = 0.;
-
-    let mut last_x = width as f32 / 2.;
-    let mut last_y = height as f32 / 2.;
-    let mut first_mouse = true;
-
-    while running {
-        let seconds = glfw.get_time() as f32;
-        delta = seconds - last_frame;
-        last_frame = seconds;
-
-        new_input = old_input.clone();
-        new_input.delta_time = delta;
-        new_input.mouse_scroll = 0.;
-
-        for (_, event) in glfw::flush_messages(&events) {
-            match event {
-                glfw::WindowEvent::Key(Key::Escape, _, _, _) => running = false,
-                glfw::WindowEvent::Key(Key::W, _, Action::Press, _) => {
-                    new_input.up.ended_down = true;
-                    new_input.up.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::W, _, Action::Release, _) => {
-                    new_input.up.ended_down = false;
-                    new_input.up.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Key(Key::D, _, Action::Press, _) => {
-                    new_input.right.ended_down = true;
-                    new_input.right.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::D, _, Action::Release, _) => {
-                    new_input.right.ended_down = false;
-                    new_input.right.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Key(Key::S, _, Action::Press, _) => {
-                    new_input.down.ended_down = true;
-                    new_input.down.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::S, _, Action::Release, _) => {
-                    new_input.down.ended_down = false;
-                    new_input.down.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Key(Key::A, _, Action::Press, _) => {
-                    new_input.left.ended_down = true;
-                    new_input.left.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::A, _, Action::Release, _) => {
-                    new_input.left.ended_down = false;
-                    new_input.left.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Key(Key::Q, _, Action::Press, _) => {
-                    new_input.left_bracket.ended_down = true;
-                    new_input.left_bracket.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::Q, _, Action::Release, _) => {
-                    new_input.left_bracket.ended_down = false;
-                    new_input.left_bracket.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Key(Key::E, _, Action::Press, _) => {
-                    new_input.right_bracket.ended_down = true;
-                    new_input.right_bracket.half_transition_count += 1;
-                }
-                glfw::WindowEvent::Key(Key::E, _, Action::Release, _) => {
-                    new_input.right_bracket.ended_down = false;
-                    new_input.right_bracket.half_transition_count = 0;
-                }
-                glfw::WindowEvent::CursorPos(x_pos, y_pos) => {
-                    let x_pos = x_pos as f32;
-                    let y_pos = y_pos as f32;
-                    if first_mouse {
-                        last_x = x_pos;
-                        last_y = y_pos;
-                        first_mouse = false;
-                    }
-                    let x_offset = x_pos - last_x;
-                    let y_offset = last_y - y_pos;
-
-                    last_x = x_pos;
-                    last_y = y_pos;
-                    camera.move_mouse(x_offset, y_offset);
-                }
-                glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
-                    new_input.mouse_left.ended_down = true;
-                    new_input.mouse_left.half_transition_count += 1;
-                }
-                glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => {
-                    new_input.mouse_left.ended_down = false;
-                    new_input.mouse_left.half_transition_count = 0;
-                }
-                glfw::WindowEvent::Scroll(_, y_offset) => {
-                    new_input.mouse_scroll = y_offset as f32;
-                }
-                _ => {}
-            }
+    fn on_event(&mut self, window: &mut Window, event: &WindowEvent) {
+        if let WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
+            window.set_should_close(true);
         }
+    }
 
-        if new_input.up.ended_down {
-            camera.move_forward(new_input.delta_time);
+    fn update(&mut self, input: &Input, dt: f32) {
+        self.previous_position = self.camera.position;
+        self.previous_time = self.time;
+        if self
+            .bindings
+            .action(input, "toggle_flashlight")
+            .was_pressed()
+        {
+            self.flashlight_on = !self.flashlight_on;
         }
-
-        if new_input.down.ended_down {
-            camera.move_backward(new_input.delta_time);
+        if self.bindings.action(input, "pause_light").was_pressed() {
+            self.light_paused = !self.light_paused;
         }
-
-        if new_input.left.ended_down {
-            camera.move_left(new_input.delta_time);
+        if !self.light_paused {
+            self.time += dt;
         }
 
-        if new_input.right.ended_down {
-            camera.move_right(new_input.delta_time);
+        let speed = if self.bindings.action(input, "sprint").ended_down {
+            2.
+        } else {
+            1.
+        };
+        self.camera
+            .move_forward(self.bindings.axis(input, "move_forward") * speed * dt);
+        self.camera
+            .move_right(self.bindings.axis(input, "move_right") * speed * dt);
+
+        if input.mouse_delta != glm::vec2(0., 0.) {
+            // Screen coordinates grow downwards.
+            self.camera
+                .move_mouse(input.mouse_delta.x, -input.mouse_delta.y);
+        }
+        let look_right = self.bindings.axis(input, "look_right");
+        let look_up = self.bindings.axis(input, "look_up");
+        if look_right != 0. || look_up != 0. {
+            let speed = STICK_LOOK_SPEED / self.camera.mouse_sensitivity;
+            self.camera
+                .move_mouse(look_right * speed * dt, look_up * speed * dt);
         }
 
-        if old_input.mouse_scroll != new_input.mouse_scroll {
-            camera.change_fov(new_input.mouse_scroll);
+        let zoom = input.mouse_scroll + self.bindings.axis(input, "zoom") * TRIGGER_ZOOM_SPEED * dt;
+        if zoom != 0. {
+            self.camera.change_fov(zoom);
         }
 
-        if old_input.mouse != new_input.mouse {
-            camera.move_mouse(new_input.mouse.x, new_input.mouse.y);
+        match self.object_shader.reload_if_changed() {
+            Ok(true) => {
+                self.camera_buffer.attach(&self.object_shader);
+                set_object_constants(&self.object_shader, &self.material);
+            }
+            Ok(false) => {}
+            Err(err) => log::error!("{}", err),
+        }
+        match self.light_shader.reload_if_changed() {
+            Ok(true) => {
+                self.camera_buffer.attach(&self.light_shader);
+            }
+            Ok(false) => {}
+            Err(err) => log::error!("{}", err),
         }
+    }
 
-        // Update
-        let light_pos = glm::Vec3::new(
-            2. * seconds.cos(),
-            2. * seconds.sin() + 2. * seconds.cos(),
-            2. * seconds.sin(),
-        );
+    fn render(&mut self, alpha: f32) {
+        let mut camera = self.camera.clone();
+        camera.position = glm::lerp(&self.previous_position, &self.camera.position, alpha);
+        let time = self.previous_time + (self.time - self.previous_time) * alpha;
+
+        self.camera_block.view = camera.view_matrix();
+        self.camera_block.projection = glm::perspective(self.aspect_ratio, camera.zoom, 0.1, 100.0);
+        self.camera_block.position = camera.position;
+        self.camera_buffer.update(&self.camera_block);
+
+        self.object_shader.use_shader();
+        let mut flashlight = self.flashlight.clone();
+        flashlight.position = camera.position;
+        flashlight.direction = camera.front;
+        if !self.flashlight_on {
+            flashlight.diffuse = glm::Vec3::zeros();
+            flashlight.specular = glm::Vec3::zeros();
+        }
+        self.object_shader.set_struct("light", &flashlight);
 
-        let projection_matrix: glm::Mat4 =
-            glm::perspective(width as f32 / height as f32, camera.zoom, 0.1, 100.0);
-
-        object_shader.use_shader();
-        object_shader.set_mat4_f32("view", camera.view_matrix());
-        object_shader.set_mat4_f32("projection", projection_matrix);
-        object_shader.set_vec3_f32("lightPos", &light_pos);
-        object_shader.set_vec3_f32("cameraPos", &camera.position);
-        object_shader.set_vec3_f32("light.position", &camera.position);
-        object_shader.set_vec3_f32("light.direction", &camera.front);
-        object_shader.set_f32("light.cutOff", 12.5_f32.to_radians().cos());
-        object_shader.set_f32("light.outerCutOff", 17.5_f32.to_radians().cos());
-        object_shader.set_f32("light.constant", 1.);
-        object_shader.set_f32("light.linear", 0.09);
-        object_shader.set_f32("light.quadratic", 0.032);
-
-        light_shader.use_shader();
-        light_shader.set_mat4_f32("view", camera.view_matrix());
-        light_shader.set_mat4_f32("projection", projection_matrix);
-
-        // Render
         unsafe {
             gl::ClearColor(0.1, 0.1, 0.1, 1.);
-            // gl::ClearColor(0.5, 0.5, 0.6, 1.);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
         unsafe {
-            object_shader.use_shader();
+            self.object_shader.use_shader();
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, diffuse_texture);
+            gl::BindTexture(gl::TEXTURE_2D, self.diffuse_texture.id());
             gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, specular_texture);
-            gl::BindVertexArray(object_vao);
-            for (i, c) in cube_positions.iter().enumerate() {
+            gl::BindTexture(gl::TEXTURE_2D, self.specular_texture.id());
+            gl::BindVertexArray(self.object_vao.id());
+            for (i, c) in self.cube_positions.iter().enumerate() {
                 let angle = 20. * i as f32;
                 let model = glm::rotate(
-                    &glm::translate(&glm::Mat4::identity(), &c),
+                    &glm::translate(&glm::Mat4::identity(), c),
                     angle.to_radians(),
                     &glm::Vec3::new(1., 0.3, 0.5),
                 );
-                object_shader.set_mat4_f32("model", model);
+                self.object_shader.set("model", &model);
                 gl::DrawArrays(gl::TRIANGLES, 0, 36);
             }
         }
 
+        let light_pos = glm::Vec3::new(
+            2. * time.cos(),
+            2. * time.sin() + 2. * time.cos(),
+            2. * time.sin(),
+        );
         unsafe {
-            light_shader.use_shader();
-            gl::BindVertexArray(light_vao);
+            self.light_shader.use_shader();
+            gl::BindVertexArray(self.light_vao.id());
             let model = glm::scale(
                 &glm::translate(&glm::Mat4::identity(), &light_pos),
                 &glm::Vec3::new(0.25, 0.25, 0.25),
             );
-            light_shader.set_mat4_f32("model", model);
+            self.light_shader.set("model", &model);
             gl::DrawArrays(gl::TRIANGLES, 0, 36);
         }
 
         unsafe {
             gl::BindVertexArray(0);
         };
+    }
+}
 
-        let elapsed = glfw.get_time() as f32 - seconds;
-        if (FPS_CAP - elapsed) > 0.0 {
-            std::thread::sleep(std::time::Duration::from_millis(
-                (FPS_CAP - elapsed).floor() as u64,
-            ));
-        }
-
-        old_input = new_input;
-
-        window.swap_buffers();
-        glfw.poll_events();
+fn set_object_constants(object_shader: &Shader, material: &Material) {
+    if let Err(mismatches) = object_shader.check_struct::<Material>("material") {
+        log::warn!(
+            "Material does not match the shader: {}",
+            mismatches.join(", ")
+        );
+    }
+    if let Err(mismatches) = object_shader.check_struct::<SpotLight>("light") {
+        log::warn!(
+            "SpotLight does not match the shader: {}",
+            mismatches.join(", ")
+        );
     }
+    object_shader.use_shader();
+    object_shader.set_struct("material", material);
 }
 
-fn create_texture(image_path: &str) -> gl::types::GLuint {
-    let mut texture = 0;
+fn create_texture(image_path: &str) -> Texture {
+    let texture = Texture::new();
     unsafe {
         let image = stb_image::image::load(image_path);
         let image = match image {
@@ -301,8 +354,8 @@ fn create_texture(image_path: &str) -> gl::types::GLuint {
             LoadResult::ImageU8(image) => image,
             LoadResult::Error(_) => panic!(),
         };
-        gl::GenTextures(1, &mut texture);
-        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture.id());
+        texture.label(image_path);
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as _);
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as _);
         gl::TexParameteri(
@@ -335,7 +388,42 @@ fn create_texture(image_path: &str) -> gl::types::GLuint {
     texture
 }
 
-fn create_vao() -> (gl::types::GLuint, gl::types::GLuint) {
+/// Logs the uniforms of a shader and warns when the attributes it reads do not match the layout
+/// of the mesh drawn with it.
+fn check_shader_interface(shader: &Shader, layout: &[(gl::types::GLint, gl::types::GLenum)]) {
+    for uniform in shader.uniforms() {
+        log::debug!(
+            "uniform {} {} [{}] at {}",
+            uniform.type_name(),
+            uniform.name,
+            uniform.size,
+            uniform.location
+        );
+    }
+    for attribute in shader.attributes() {
+        match layout
+            .iter()
+            .find(|(location, _)| *location == attribute.location)
+        {
+            Some((_, kind)) if *kind == attribute.kind => {}
+            Some((_, kind)) => log::warn!(
+                "attribute {} is a {} but the mesh provides a {}",
+                attribute.name,
+                attribute.type_name(),
+                reflection::type_name(*kind)
+            ),
+            None => log::warn!(
+                "attribute {} at location {} is not provided by the mesh",
+                attribute.name,
+                attribute.location
+            ),
+        }
+    }
+}
+
+/// Uploads the cube vertices and returns the buffer with the vertex arrays of the objects and
+/// of the light, which both read from it.
+fn create_vao() -> (Buffer, VertexArray, VertexArray) {
     let vertices: Vec<f32> = vec![
         -0.5, -0.5, -0.5, 0., 0., -1., 0., 0., //
         0.5, -0.5, -0.5, 0., 0., -1., 1., 0., //
@@ -379,15 +467,14 @@ fn create_vao() -> (gl::types::GLuint, gl::types::GLuint) {
         -0.5, 0.5, 0.5, 0., 1., 0., 0., 0., //
         -0.5, 0.5, -0.5, 0., 1., 0., 0., 1.0, //
     ];
-    let mut vao = 0;
-    let mut light_vao = 0;
+    let vbo = Buffer::new();
+    let vao = VertexArray::new();
+    let light_vao = VertexArray::new();
     unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BindVertexArray(vao.id());
+        vao.label("object vertex array");
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo.id());
+        vbo.label("cube vertices");
         gl::BufferData(
             gl::ARRAY_BUFFER,
             (vertices.len() * std::mem::size_of::<f32>()) as _,
@@ -422,9 +509,9 @@ fn create_vao() -> (gl::types::GLuint, gl::types::GLuint) {
         );
         gl::EnableVertexAttribArray(2);
 
-        gl::GenVertexArrays(1, &mut light_vao);
-        gl::BindVertexArray(light_vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BindVertexArray(light_vao.id());
+        light_vao.label("light vertex array");
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo.id());
 
         gl::VertexAttribPointer(
             0,
@@ -439,5 +526,5 @@ fn create_vao() -> (gl::types::GLuint, gl::types::GLuint) {
         gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         gl::BindVertexArray(0);
     }
-    (vao, light_vao)
+    (vbo, vao, light_vao)
 }
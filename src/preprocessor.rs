@@ -0,0 +1,369 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::shader::ShaderError;
+use crate::shader_source;
+
+/// GLSL source with every `#include "file"` directive expanded.
+///
+/// Each file gets its own source string number in the emitted `#line` directives, so compiler
+/// messages can be mapped back with [`Preprocessed::remap_log`].
+pub struct Preprocessed {
+    pub source: String,
+    pub files: Vec<PathBuf>,
+}
+
+impl Preprocessed {
+    /// Replaces the source string numbers in a driver info log with the file they refer to.
+    ///
+    /// Drivers format locations differently (`0:12(3)` on Mesa, `0(12)` on NVIDIA), but all of
+    /// them start with the source string number followed by `:` or `(` and the line.
+    pub fn remap_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.remap_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn remap_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            if !bytes[start].is_ascii_digit() || (start > 0 && bytes[start - 1].is_ascii_digit()) {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+            let followed_by_line = end + 1 < bytes.len()
+                && (bytes[end] == b':' || bytes[end] == b'(')
+                && bytes[end + 1].is_ascii_digit();
+            if followed_by_line {
+                if let Some(file) = line[start..end]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.files.get(index))
+                {
+                    return format!("{}{}{}", &line[..start], file.display(), &line[end..]);
+                }
+                return line.to_string();
+            }
+            start = end;
+        }
+        line.to_string()
+    }
+}
+
+/// A set of `#define`s selecting one variant of a shader.
+///
+/// Defines are kept sorted, so two sets with the same entries compare and hash equal no matter
+/// the order they were added in.
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `#define name`, for features checked with `#ifdef`.
+    pub fn with(self, name: &str) -> Self {
+        self.with_value(name, "")
+    }
+
+    /// Adds `#define name value`.
+    pub fn with_value(mut self, name: &str, value: &str) -> Self {
+        self.0.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Reads `path` and recursively expands its `#include "file"` directives.
+///
+/// Include paths are relative to the including file. A file is only expanded the first time it
+/// is included, like a header with include guards, and including a file that is still being
+/// expanded is reported as a cycle.
+///
+/// `defines` are inserted right after the `#version` line, which has to stay first.
+pub fn preprocess(path: &str, defines: &Defines) -> Result<Preprocessed, ShaderError> {
+    let mut preprocessor = Preprocessor {
+        output: String::new(),
+        files: Vec::new(),
+        stack: Vec::new(),
+        defines,
+    };
+    preprocessor.expand(Path::new(path))?;
+    Ok(Preprocessed {
+        source: preprocessor.output,
+        files: preprocessor.files,
+    })
+}
+
+struct Preprocessor<'a> {
+    output: String,
+    files: Vec<PathBuf>,
+    stack: Vec<PathBuf>,
+    defines: &'a Defines,
+}
+
+impl Preprocessor<'_> {
+    fn expand(&mut self, path: &Path) -> Result<(), ShaderError> {
+        let source = shader_source::read(path).map_err(|source| ShaderError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let index = self.files.len();
+        self.files.push(path.to_path_buf());
+        self.stack.push(canonical(path));
+
+        if index > 0 {
+            self.output.push_str(&format!("#line 1 {}\n", index));
+        } else if !self.defines.is_empty() && !source.lines().any(is_version) {
+            self.push_defines(1);
+        }
+        for (number, line) in source.lines().enumerate() {
+            if index == 0 && !self.defines.is_empty() && is_version(line) {
+                self.output.push_str(line);
+                self.output.push('\n');
+                self.push_defines(number + 2);
+                continue;
+            }
+            let include = match include_target(line) {
+                Some(include) => include,
+                None => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                    continue;
+                }
+            };
+            let include = include.map_err(|message| ShaderError::Preprocess {
+                path: path.display().to_string(),
+                line: number + 1,
+                message,
+            })?;
+            let include_path = path.parent().unwrap_or_else(|| Path::new("")).join(include);
+            let canonical_include = canonical(&include_path);
+            if self.stack.contains(&canonical_include) {
+                return Err(ShaderError::Preprocess {
+                    path: path.display().to_string(),
+                    line: number + 1,
+                    message: format!("include cycle through {}", include_path.display()),
+                });
+            }
+            if self
+                .files
+                .iter()
+                .any(|file| canonical(file) == canonical_include)
+            {
+                // Keep the line so the numbering of the rest of the file is untouched.
+                self.output.push('\n');
+                continue;
+            }
+            self.expand(&include_path)?;
+            self.output
+                .push_str(&format!("#line {} {}\n", number + 2, index));
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Emits the defines, then restores the numbering of the root file at `next_line`.
+    fn push_defines(&mut self, next_line: usize) {
+        for (name, value) in &self.defines.0 {
+            if value.is_empty() {
+                self.output.push_str(&format!("#define {}\n", name));
+            } else {
+                self.output
+                    .push_str(&format!("#define {} {}\n", name, value));
+            }
+        }
+        self.output.push_str(&format!("#line {} 0\n", next_line));
+    }
+}
+
+pub(crate) fn is_version(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|directive| directive.trim_start().starts_with("version"))
+}
+
+/// Returns the quoted file name if `line` is an `#include` directive.
+fn include_target(line: &str) -> Option<Result<&str, String>> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let argument = directive.strip_prefix("include")?.trim();
+    let target = argument
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|target| !target.is_empty() && !target.contains('"'))
+        .ok_or_else(|| format!("malformed include directive `{}`", line.trim()));
+    Some(target)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| shader_source::normalize(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_files;
+
+    fn preprocess_file(dir: &Path, name: &str) -> Result<Preprocessed, ShaderError> {
+        preprocess(&dir.join(name).display().to_string(), &Defines::new())
+    }
+
+    #[test]
+    fn includes_are_expanded_between_line_markers() {
+        let dir = write_files(
+            "preprocessor-expand",
+            &[
+                (
+                    "main.fs",
+                    "#version 330 core\n#include \"common.glsl\"\nvoid main() {}\n",
+                ),
+                ("common.glsl", "float x;\n"),
+            ],
+        );
+        let preprocessed = preprocess_file(&dir, "main.fs").unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "#version 330 core\n#line 1 1\nfloat x;\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(
+            preprocessed.files,
+            [dir.join("main.fs"), dir.join("common.glsl")]
+        );
+    }
+
+    #[test]
+    fn files_are_included_once_relative_to_the_includer() {
+        let dir = write_files(
+            "preprocessor-once",
+            &[
+                (
+                    "main.fs",
+                    "#version 330 core\n#include \"a.glsl\"\n#include \"lib/b.glsl\"\nvoid main() {}\n",
+                ),
+                ("a.glsl", "float a;\n"),
+                ("lib/b.glsl", "#include \"../a.glsl\"\nfloat b;\n"),
+            ],
+        );
+        let preprocessed = preprocess_file(&dir, "main.fs").unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "#version 330 core\n\
+             #line 1 1\nfloat a;\n#line 3 0\n\
+             #line 1 2\n\nfloat b;\n#line 4 0\n\
+             void main() {}\n"
+        );
+        assert_eq!(preprocessed.files.len(), 3);
+    }
+
+    #[test]
+    fn include_cycles_are_reported_where_they_close() {
+        let dir = write_files(
+            "preprocessor-cycle",
+            &[
+                ("a.glsl", "#include \"b.glsl\"\n"),
+                ("b.glsl", "float b;\n#include \"a.glsl\"\n"),
+            ],
+        );
+        match preprocess_file(&dir, "a.glsl") {
+            Err(ShaderError::Preprocess {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!(path, dir.join("b.glsl").display().to_string());
+                assert_eq!(line, 2);
+                assert_eq!(
+                    message,
+                    format!("include cycle through {}", dir.join("a.glsl").display())
+                );
+            }
+            _ => panic!("the cycle was not reported"),
+        }
+    }
+
+    #[test]
+    fn malformed_includes_are_errors() {
+        let dir = write_files(
+            "preprocessor-malformed",
+            &[("main.fs", "\n#include <common.glsl>\n")],
+        );
+        match preprocess_file(&dir, "main.fs") {
+            Err(ShaderError::Preprocess { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(
+                    message,
+                    "malformed include directive `#include <common.glsl>`"
+                );
+            }
+            _ => panic!("the include was accepted"),
+        }
+    }
+
+    fn preprocess_with_defines(test: &str, source: &str) -> String {
+        let dir = write_files(&format!("preprocessor-{}", test), &[("main.fs", source)]);
+        let defines = Defines::new().with("SPOT_LIGHT").with_value("LIGHTS", "4");
+        preprocess(&dir.join("main.fs").display().to_string(), &defines)
+            .unwrap()
+            .source
+    }
+
+    #[test]
+    fn defines_follow_the_version_line() {
+        assert_eq!(
+            preprocess_with_defines("defines", "#version 330 core\nvoid main() {}\n"),
+            "#version 330 core\n#define LIGHTS 4\n#define SPOT_LIGHT\n#line 2 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_come_first_without_a_version_line() {
+        assert_eq!(
+            preprocess_with_defines("defines-no-version", "void main() {}\n"),
+            "#define LIGHTS 4\n#define SPOT_LIGHT\n#line 1 0\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_skip_comments_and_blank_lines_before_the_version() {
+        assert_eq!(
+            preprocess_with_defines(
+                "defines-comment",
+                "// #version 100\n\n  #  version 330 core\nvoid main() {}\n"
+            ),
+            "// #version 100\n\n  #  version 330 core\n\
+             #define LIGHTS 4\n#define SPOT_LIGHT\n#line 4 0\n\
+             void main() {}\n"
+        );
+    }
+
+    #[test]
+    fn logs_name_the_file_of_each_source_string() {
+        let preprocessed = Preprocessed {
+            source: String::new(),
+            files: vec![PathBuf::from("main.fs"), PathBuf::from("light.glsl")],
+        };
+        let log = "0:12(3): error: `x' undeclared\n\
+                   1(4) : error C1008: undefined variable \"y\"\n\
+                   ERROR: 2:1: out of range\n\
+                   warning: no location 10";
+        assert_eq!(
+            preprocessed.remap_log(log),
+            "main.fs:12(3): error: `x' undeclared\n\
+             light.glsl(4) : error C1008: undefined variable \"y\"\n\
+             ERROR: 2:1: out of range\n\
+             warning: no location 10"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::ffi::CStr;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::gl_object::Program;
+use crate::shader::{gl_version, has_extension, ShaderStage};
+
+/// On-disk cache of linked program binaries, so unchanged programs skip compiling and linking
+/// on startup.
+///
+/// Entries are keyed by the preprocessed sources and the driver, because a binary is only valid
+/// for the driver version that produced it. A driver can still reject a binary it wrote, for
+/// example after an update that kept the version string; the caller then links from source and
+/// the entry is replaced.
+#[derive(Clone, Debug)]
+pub struct ProgramCache {
+    dir: PathBuf,
+}
+
+impl ProgramCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Whether the context can save and load program binaries, which needs GL 4.1 or
+    /// `ARB_get_program_binary`, and at least one binary format.
+    pub(crate) fn is_supported() -> bool {
+        let mut formats = 0;
+        unsafe {
+            if gl_version() < (4, 1) && !has_extension("GL_ARB_get_program_binary") {
+                return false;
+            }
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        formats > 0
+    }
+
+    /// Hashes the preprocessed source of every stage together with the driver strings.
+    pub(crate) fn key(sources: &[(ShaderStage, &str)]) -> u64 {
+        let mut hash = Fnv1a::new();
+        for name in [gl::VENDOR, gl::RENDERER, gl::VERSION] {
+            hash.write(gl_string(name).as_bytes());
+        }
+        for (stage, source) in sources {
+            hash.write(stage.to_string().as_bytes());
+            hash.write(source.as_bytes());
+        }
+        hash.0
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key))
+    }
+
+    /// Creates a program from the cached binary for `key`. Returns `None` if there is no entry
+    /// or the driver rejects it, in which case the entry is removed.
+    pub(crate) fn load(&self, key: u64) -> Option<Program> {
+        let path = self.path(key);
+        let data = fs::read(&path).ok()?;
+        if data.len() < 4 {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let (format, binary) = data.split_at(4);
+        let format = u32::from_le_bytes(format.try_into().unwrap());
+        let program = Program::new();
+        let mut linked = 0;
+        unsafe {
+            gl::ProgramBinary(
+                program.id(),
+                format,
+                binary.as_ptr().cast(),
+                binary.len() as gl::types::GLsizei,
+            );
+            gl::GetProgramiv(program.id(), gl::LINK_STATUS, &mut linked);
+        }
+        if linked == 0 {
+            log::info!(
+                "cached program {} was rejected, linking from source",
+                path.display()
+            );
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(program)
+    }
+
+    /// Saves the binary of a linked program. The program has to be linked with
+    /// `PROGRAM_BINARY_RETRIEVABLE_HINT` set.
+    pub(crate) fn store(&self, key: u64, program: &Program) {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(program.id(), gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 {
+            return;
+        }
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                program.id(),
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr().cast(),
+            );
+        }
+        binary.truncate(written as usize);
+        let mut data = format.to_le_bytes().to_vec();
+        data.extend_from_slice(&binary);
+        let path = self.path(key);
+        let result = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, data));
+        if let Err(err) = result {
+            log::warn!("cannot write program cache {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string.cast()).to_string_lossy().into_owned()
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is guaranteed to give the same hash across Rust
+/// releases, so cache entries survive a toolchain update.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        // Separate the fields, so moving text from one source to the next changes the hash.
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+}
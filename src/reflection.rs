@@ -0,0 +1,141 @@
+use crate::shader::to_cstring;
+
+/// An active uniform of a linked program, as reported by `glGetActiveUniform`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UniformInfo {
+    /// Full name, e.g. `material.shininess` or `lights[0]` for arrays.
+    pub name: String,
+    /// GL type enum, e.g. `gl::FLOAT_VEC3` or `gl::SAMPLER_2D`.
+    pub kind: gl::types::GLenum,
+    /// Number of elements, 1 unless the uniform is an array.
+    pub size: i32,
+    /// -1 for members of a uniform block, which are not set through locations.
+    pub location: gl::types::GLint,
+}
+
+impl UniformInfo {
+    pub fn type_name(&self) -> &'static str {
+        type_name(self.kind)
+    }
+}
+
+/// An active vertex attribute of a linked program, as reported by `glGetActiveAttrib`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub kind: gl::types::GLenum,
+    /// -1 for built-ins such as `gl_VertexID`.
+    pub location: gl::types::GLint,
+}
+
+impl AttributeInfo {
+    pub fn type_name(&self) -> &'static str {
+        type_name(self.kind)
+    }
+}
+
+/// GLSL spelling of a GL type enum, or `"unknown"` for types this engine does not handle.
+pub fn type_name(kind: gl::types::GLenum) -> &'static str {
+    match kind {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::DOUBLE => "double",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        _ => "unknown",
+    }
+}
+
+pub(crate) unsafe fn active_uniforms(program: gl::types::GLuint) -> Vec<UniformInfo> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut name: Vec<u8> = vec![0; max_length.max(1) as usize];
+    (0..count as gl::types::GLuint)
+        .map(|index| {
+            let mut length = 0;
+            let mut size = 0;
+            let mut kind = 0;
+            gl::GetActiveUniform(
+                program,
+                index,
+                name.len() as _,
+                &mut length,
+                &mut size,
+                &mut kind,
+                name.as_mut_ptr() as _,
+            );
+            let name = String::from_utf8_lossy(&name[..length as usize]).into_owned();
+            let location = gl::GetUniformLocation(program, to_cstring(&name).as_ptr());
+            UniformInfo {
+                name,
+                kind,
+                size,
+                location,
+            }
+        })
+        .collect()
+}
+
+pub(crate) unsafe fn active_attributes(program: gl::types::GLuint) -> Vec<AttributeInfo> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+    let mut name: Vec<u8> = vec![0; max_length.max(1) as usize];
+    let mut attributes: Vec<AttributeInfo> = (0..count as gl::types::GLuint)
+        .map(|index| {
+            let mut length = 0;
+            let mut size = 0;
+            let mut kind = 0;
+            gl::GetActiveAttrib(
+                program,
+                index,
+                name.len() as _,
+                &mut length,
+                &mut size,
+                &mut kind,
+                name.as_mut_ptr() as _,
+            );
+            let name = String::from_utf8_lossy(&name[..length as usize]).into_owned();
+            let location = gl::GetAttribLocation(program, to_cstring(&name).as_ptr());
+            AttributeInfo {
+                name,
+                kind,
+                location,
+            }
+        })
+        .collect();
+    attributes.sort_by_key(|attribute| attribute.location);
+    attributes
+}
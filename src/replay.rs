@@ -0,0 +1,281 @@
+//! Recording of the [`Input`] of every update, and replaying it in place of the devices.
+//!
+//! With the fixed timestep every update sees exactly the recorded input and `dt`, so a replay
+//! reproduces the simulation of the recorded run update by update, no matter how fast frames
+//! are rendered. Anything an application reads outside of [`Input`], like window events, is
+//! not recorded.
+//!
+//! A recording is a text file with one RON value per line: a header, then one line per update.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_map::Binding;
+use crate::utils::{ButtonState, Input};
+
+/// Bumped when the format of the lines changes.
+const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    update_rate: f32,
+}
+
+/// One update, leaving out the buttons that are up and unchanged.
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    delta_time: f32,
+    buttons: Vec<(Binding, ButtonState)>,
+    gamepad_axes: [f32; 6],
+    gamepad_connected: bool,
+    mouse: (f32, f32),
+    mouse_delta: (f32, f32),
+    mouse_scroll: f32,
+}
+
+impl Frame {
+    fn new(input: &Input) -> Self {
+        let keys = input
+            .keys
+            .iter()
+            .map(|(key, state)| (Binding::Key(*key), *state));
+        let mouse_buttons = input
+            .mouse_buttons
+            .iter()
+            .map(|(button, state)| (Binding::MouseButton(*button), *state));
+        let gamepad_buttons = input
+            .gamepad_buttons
+            .iter()
+            .map(|(button, state)| (Binding::GamepadButton(*button), *state));
+        let mut buttons: Vec<_> = keys
+            .chain(mouse_buttons)
+            .chain(gamepad_buttons)
+            .filter(|(_, state)| *state != ButtonState::default())
+            .collect();
+        // The maps iterate in random order, sorting keeps recordings of the same run equal.
+        buttons.sort_by_key(|(binding, _)| binding.to_string());
+        Self {
+            delta_time: input.delta_time,
+            buttons,
+            gamepad_axes: input.gamepad_axes,
+            gamepad_connected: input.gamepad_connected,
+            mouse: (input.mouse.x, input.mouse.y),
+            mouse_delta: (input.mouse_delta.x, input.mouse_delta.y),
+            mouse_scroll: input.mouse_scroll,
+        }
+    }
+
+    fn into_input(self) -> Input {
+        let mut input = Input::new(self.mouse.0, self.mouse.1);
+        for (binding, state) in self.buttons {
+            match binding {
+                Binding::Key(key) => input.keys.insert(key, state),
+                Binding::MouseButton(button) => input.mouse_buttons.insert(button, state),
+                Binding::GamepadButton(button) => input.gamepad_buttons.insert(button, state),
+            };
+        }
+        input.gamepad_axes = self.gamepad_axes;
+        input.gamepad_connected = self.gamepad_connected;
+        input.mouse_delta = glm::vec2(self.mouse_delta.0, self.mouse_delta.1);
+        input.mouse_scroll = self.mouse_scroll;
+        input.delta_time = self.delta_time;
+        input
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(PathBuf, io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        error: Box<ron::error::SpannedError>,
+    },
+    Serialize(ron::Error),
+    Empty(PathBuf),
+    Version(PathBuf, u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ReplayError::Parse { path, line, error } => {
+                write!(f, "{}:{}: {}", path.display(), line, error.code)
+            }
+            ReplayError::Serialize(err) => write!(f, "cannot serialize input: {}", err),
+            ReplayError::Empty(path) => write!(f, "{}: the recording is empty", path.display()),
+            ReplayError::Version(path, version) => write!(
+                f,
+                "{}: recorded with format version {}, but only version {} can be replayed",
+                path.display(),
+                version,
+                VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Writes the input of every update to a file.
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, update_rate: f32) -> Result<Self, ReplayError> {
+        let path = path.as_ref().to_owned();
+        let file = File::create(&path).map_err(|err| ReplayError::Io(path.clone(), err))?;
+        let mut recorder = Self {
+            path,
+            writer: BufWriter::new(file),
+        };
+        recorder.write_line(&Header {
+            version: VERSION,
+            update_rate,
+        })?;
+        Ok(recorder)
+    }
+
+    /// Appends the input one update is run with.
+    pub fn record(&mut self, input: &Input) -> Result<(), ReplayError> {
+        self.write_line(&Frame::new(input))
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> Result<(), ReplayError> {
+        let line = ron::to_string(value).map_err(ReplayError::Serialize)?;
+        writeln!(self.writer, "{}", line).map_err(|err| ReplayError::Io(self.path.clone(), err))
+    }
+}
+
+/// The recorded inputs of a file, one per update.
+pub struct Replay {
+    update_rate: f32,
+    frames: std::vec::IntoIter<Input>,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let source =
+            fs::read_to_string(path).map_err(|err| ReplayError::Io(path.to_owned(), err))?;
+        let parse_error = |line: usize, error| ReplayError::Parse {
+            path: path.to_owned(),
+            line: line + 1,
+            error: Box::new(error),
+        };
+
+        let mut lines = source.lines().enumerate();
+        let header: Header = match lines.next() {
+            Some((line, text)) => ron::from_str(text).map_err(|err| parse_error(line, err))?,
+            None => return Err(ReplayError::Empty(path.to_owned())),
+        };
+        if header.version != VERSION {
+            return Err(ReplayError::Version(path.to_owned(), header.version));
+        }
+        let frames = lines
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(line, text)| {
+                ron::from_str::<Frame>(text)
+                    .map(Frame::into_input)
+                    .map_err(|err| parse_error(line, err))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            update_rate: header.update_rate,
+            frames: frames.into_iter(),
+        })
+    }
+
+    /// Updates per second of the recorded run.
+    pub fn update_rate(&self) -> f32 {
+        self.update_rate
+    }
+
+    /// Updates left to replay.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl Iterator for Replay {
+    type Item = Input;
+
+    fn next(&mut self) -> Option<Input> {
+        self.frames.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use glfw::{Action, Key, WindowEvent};
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let path = std::env::temp_dir().join(format!("replay_test_{}.ron", std::process::id()));
+        let mut input = Input::new(10., 20.);
+        input.delta_time = 1. / 60.;
+
+        let mut recorder = Recorder::create(&path, 60.).unwrap();
+        recorder.record(&input).unwrap();
+        input.process_event(&WindowEvent::Key(
+            Key::W,
+            0,
+            Action::Press,
+            glfw::Modifiers::empty(),
+        ));
+        input.process_event(&WindowEvent::CursorPos(15., 18.));
+        input.process_event(&WindowEvent::CursorPos(16., 17.));
+        input.process_event(&WindowEvent::Scroll(0., 2.));
+        input.gamepad_axes[0] = 0.5;
+        recorder.record(&input).unwrap();
+        drop(recorder);
+
+        let mut replay = Replay::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.update_rate(), 60.);
+        assert_eq!(replay.remaining(), 2);
+
+        let first = replay.next().unwrap();
+        assert!(!first.key(Key::W).ended_down);
+        assert_eq!(first.mouse, glm::vec2(10., 20.));
+
+        let second = replay.next().unwrap();
+        assert_eq!(second.key(Key::W), input.key(Key::W));
+        assert_eq!(second.mouse, glm::vec2(16., 17.));
+        assert_eq!(second.mouse_delta, glm::vec2(6., -3.));
+        assert_eq!(second.mouse_scroll, 2.);
+        assert_eq!(second.gamepad_axes[0], 0.5);
+        assert_eq!(second.delta_time, 1. / 60.);
+        assert!(replay.next().is_none());
+    }
+
+    #[test]
+    fn unmapped_keys_do_not_break_the_recording() {
+        let path = std::env::temp_dir().join(format!("replay_unknown_{}.ron", std::process::id()));
+        let mut input = Input::new(0., 0.);
+        input.process_event(&WindowEvent::Key(
+            Key::Unknown,
+            160,
+            Action::Press,
+            glfw::Modifiers::empty(),
+        ));
+
+        let mut recorder = Recorder::create(&path, 60.).unwrap();
+        recorder.record(&input).unwrap();
+        drop(recorder);
+
+        let mut replay = Replay::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!replay.next().unwrap().key(Key::Unknown).ended_down);
+    }
+}
@@ -1,129 +1,726 @@
-use std::ffi::CString;
-use std::fs;
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::gl_debug;
+use crate::gl_object::Program;
+use crate::glsl::{self, GlslError};
+use crate::preprocessor::{self, Defines, Preprocessed};
+use crate::program_cache::ProgramCache;
+use crate::reflection::{self, AttributeInfo, UniformInfo};
+use crate::shader_source;
+use crate::uniform::{Uniform, UniformStruct};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    fn gl_type(self) -> gl::types::GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    /// The OpenGL version the stage became core in, and the extension that provides it on
+    /// older contexts, for the stages a 3.3 context does not have.
+    fn requirement(self) -> Option<((i32, i32), &'static str)> {
+        match self {
+            ShaderStage::TessControl | ShaderStage::TessEvaluation => {
+                Some(((4, 0), "GL_ARB_tessellation_shader"))
+            }
+            ShaderStage::Compute => Some(((4, 3), "GL_ARB_compute_shader")),
+            _ => None,
+        }
+    }
+
+    /// Whether the current context can compile this stage.
+    pub fn is_supported(self) -> bool {
+        self.requirement()
+            .is_none_or(|(version, extension)| unsafe {
+                gl_version() >= version || has_extension(extension)
+            })
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::TessControl => "tessellation control",
+            ShaderStage::TessEvaluation => "tessellation evaluation",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Compute => "compute",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Compile {
+        stage: ShaderStage,
+        path: String,
+        log: String,
+    },
+    Link {
+        log: String,
+    },
+    Preprocess {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    /// The context is too old for the stage, see [`ShaderStage::is_supported`].
+    Unsupported {
+        stage: ShaderStage,
+        version: (i32, i32),
+        extension: &'static str,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, source } => write!(f, "cannot read {}: {}", path, source),
+            ShaderError::Compile { stage, path, log } => {
+                write!(f, "{} shader {} not compiled:\n{}", stage, path, log)
+            }
+            ShaderError::Link { log } => write!(f, "program not linked:\n{}", log),
+            ShaderError::Preprocess {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            ShaderError::Unsupported {
+                stage,
+                version: (major, minor),
+                extension,
+            } => write!(
+                f,
+                "{} shaders need OpenGL {}.{} or {}, which the context does not have",
+                stage, major, minor, extension
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 pub struct Shader {
-    id: gl::types::GLuint,
+    program: Program,
+    source_files: Vec<PathBuf>,
+    uniforms: Vec<UniformInfo>,
+    attributes: Vec<AttributeInfo>,
+    uniform_locations: HashMap<String, gl::types::GLint>,
+    missing_uniforms: RefCell<HashSet<String>>,
+    /// Member locations of the struct uniforms set so far, by struct type and uniform name, so
+    /// the member names are only joined the first time.
+    struct_locations: RefCell<StructLocations>,
 }
 
+type StructLocations = HashMap<TypeId, HashMap<String, Vec<Option<gl::types::GLint>>>>;
+
 impl Shader {
-    pub fn new(vertex_shader_path: &str, fragment_shader_path: &str) -> Self {
-        let id;
-        let vertex_shader = Self::compile_shader(vertex_shader_path, gl::VERTEX_SHADER);
-        let fragment_shader = Self::compile_shader(fragment_shader_path, gl::FRAGMENT_SHADER);
-        id = Self::link_program(vertex_shader, fragment_shader);
-        Self { id }
+    pub fn new(vertex_shader_path: &str, fragment_shader_path: &str) -> Result<Self, ShaderError> {
+        ShaderBuilder::new()
+            .vertex(vertex_shader_path)
+            .fragment(fragment_shader_path)
+            .build()
     }
 
-    fn compile_shader(path: &str, shader_type: gl::types::GLenum) -> gl::types::GLuint {
+    fn from_stages(
+        stages: &[(ShaderStage, String)],
+        defines: &Defines,
+        cache: Option<&ProgramCache>,
+    ) -> Result<Self, ShaderError> {
+        for (stage, _) in stages {
+            if let Some((version, extension)) =
+                stage.requirement().filter(|_| !stage.is_supported())
+            {
+                return Err(ShaderError::Unsupported {
+                    stage: *stage,
+                    version,
+                    extension,
+                });
+            }
+        }
+        let mut source_files = Vec::new();
+        let mut sources = Vec::with_capacity(stages.len());
+        for (_, path) in stages {
+            let preprocessed = preprocessor::preprocess(path, defines)?;
+            source_files.extend(preprocessed.files.iter().cloned());
+            sources.push(preprocessed);
+        }
+        let cache = cache.filter(|_| ProgramCache::is_supported()).map(|cache| {
+            let keyed: Vec<(ShaderStage, &str)> = stages
+                .iter()
+                .zip(&sources)
+                .map(|((stage, _), preprocessed)| (*stage, preprocessed.source.as_str()))
+                .collect();
+            (cache, ProgramCache::key(&keyed))
+        });
+
+        let cached = cache.and_then(|(cache, key)| cache.load(key));
+        let program = match cached {
+            Some(program) => program,
+            None => {
+                let mut shaders = Vec::with_capacity(stages.len());
+                for ((stage, path), preprocessed) in stages.iter().zip(&sources) {
+                    match Self::compile_shader(path, *stage, preprocessed) {
+                        Ok(shader) => shaders.push(shader),
+                        Err(err) => {
+                            for shader in shaders {
+                                unsafe {
+                                    gl::DeleteShader(shader);
+                                }
+                            }
+                            return Err(err);
+                        }
+                    }
+                }
+                let program = Self::link_program(&shaders, cache.is_some())?;
+                if let Some((cache, key)) = cache {
+                    cache.store(key, &program);
+                }
+                program
+            }
+        };
+        program.label(
+            &stages
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        let (uniforms, attributes) = unsafe {
+            (
+                reflection::active_uniforms(program.id()),
+                reflection::active_attributes(program.id()),
+            )
+        };
+        let uniform_locations = unsafe { uniform_locations(program.id(), &uniforms) };
+        Ok(Self {
+            program,
+            source_files,
+            uniforms,
+            attributes,
+            uniform_locations,
+            missing_uniforms: RefCell::new(HashSet::new()),
+            struct_locations: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Every file read to build this program, including the ones pulled in by `#include`.
+    pub fn source_files(&self) -> &[PathBuf] {
+        &self.source_files
+    }
+
+    /// Assigns the uniform block `name` to a uniform buffer binding point. Returns `false` if the
+    /// program has no such active block.
+    pub fn bind_uniform_block(&self, name: &str, binding: gl::types::GLuint) -> bool {
+        unsafe {
+            let index = gl::GetUniformBlockIndex(self.program.id(), to_cstring(name).as_ptr());
+            if index == gl::INVALID_INDEX {
+                return false;
+            }
+            gl::UniformBlockBinding(self.program.id(), index, binding);
+        }
+        true
+    }
+
+    /// Active uniforms of the linked program, including members of uniform blocks.
+    pub fn uniforms(&self) -> &[UniformInfo] {
+        &self.uniforms
+    }
+
+    /// Active vertex attributes of the linked program, sorted by location.
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    fn compile_shader(
+        path: &str,
+        stage: ShaderStage,
+        preprocessed: &Preprocessed,
+    ) -> Result<gl::types::GLuint, ShaderError> {
+        let source = to_source_cstring(path, &preprocessed.source)?;
         let shader;
         unsafe {
-            shader = gl::CreateShader(shader_type);
-            gl::ShaderSource(shader, 1, &read_file(path).as_ptr(), std::ptr::null());
+            shader = gl::CreateShader(stage.gl_type());
+            gl_debug::label(gl::SHADER, shader, path);
+            gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
             gl::CompileShader(shader);
             let mut compiled = 0;
             gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compiled);
-            let mut log_length = 0;
-            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_length);
-            let mut info_log: Vec<u8> = Vec::with_capacity(log_length as usize);
-            gl::GetShaderInfoLog(
-                shader,
-                log_length,
-                std::ptr::null_mut(),
-                info_log.as_mut_ptr() as _,
-            );
             if compiled == 0 {
-                panic!(
-                    "{} not compiled {}",
-                    path,
-                    String::from_utf8_unchecked(info_log)
-                );
+                let log = preprocessed.remap_log(&shader_info_log(shader));
+                gl::DeleteShader(shader);
+                return Err(ShaderError::Compile {
+                    stage,
+                    path: path.to_string(),
+                    log,
+                });
             }
         }
-        shader
+        Ok(shader)
     }
 
+    /// Links the compiled stages. `retrievable` asks the driver to keep the program binary
+    /// around for [`ProgramCache::store`].
     fn link_program(
-        vertex_shader: gl::types::GLuint,
-        fragment_shader: gl::types::GLuint,
-    ) -> gl::types::GLuint {
-        let program;
+        shaders: &[gl::types::GLuint],
+        retrievable: bool,
+    ) -> Result<Program, ShaderError> {
+        let program = Program::new();
         unsafe {
-            program = gl::CreateProgram();
-            gl::AttachShader(program, vertex_shader);
-            gl::AttachShader(program, fragment_shader);
-            gl::LinkProgram(program);
-            let mut compiled = 0;
-            gl::GetProgramiv(program, gl::LINK_STATUS, &mut compiled);
-            let mut log_length = 0;
-            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_length);
-            let mut info_log: Vec<u8> = Vec::with_capacity(log_length as usize);
-            gl::GetShaderInfoLog(
-                program,
-                log_length,
-                std::ptr::null_mut(),
-                info_log.as_mut_ptr() as _,
-            );
-            if compiled == 0 {
-                panic!("Not linked {}", String::from_utf8_unchecked(info_log));
+            if retrievable {
+                gl::ProgramParameteri(
+                    program.id(),
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as gl::types::GLint,
+                );
+            }
+            for shader in shaders {
+                gl::AttachShader(program.id(), *shader);
+            }
+            gl::LinkProgram(program.id());
+            for shader in shaders {
+                gl::DeleteShader(*shader);
+            }
+            let mut linked = 0;
+            gl::GetProgramiv(program.id(), gl::LINK_STATUS, &mut linked);
+            if linked == 0 {
+                let log = program_info_log(program.id());
+                return Err(ShaderError::Link { log });
             }
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
         }
-        program
+        Ok(program)
     }
 
     pub fn use_shader(&self) {
         unsafe {
-            gl::UseProgram(self.id);
+            gl::UseProgram(self.program.id());
         }
     }
 
-    pub fn set_i32(&self, name: &str, value: i32) {
-        unsafe {
-            let location = gl::GetUniformLocation(self.id, to_cstring(name).as_ptr());
-            gl::Uniform1i(location, value);
+    /// Looks up a uniform in the locations cached at link time.
+    ///
+    /// Setting a uniform the program does not have is almost always a typo or a uniform the
+    /// compiler optimized away, so it is reported once per name instead of being ignored.
+    fn uniform_location(&self, name: &str) -> Option<gl::types::GLint> {
+        let location = self.uniform_locations.get(name).copied();
+        if location.is_none() && self.missing_uniforms.borrow_mut().insert(name.to_string()) {
+            log::warn!(
+                "{} is not an active uniform of program {} ({})",
+                name,
+                self.program.id(),
+                self.source_files
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
         }
+        location
     }
 
-    pub fn set_f32(&self, name: &str, value: f32) {
-        unsafe {
-            let location = gl::GetUniformLocation(self.id, to_cstring(name).as_ptr());
-            gl::Uniform1f(location, value);
+    /// Uploads `value` to the uniform `name`. The shader has to be in use.
+    pub fn set<T: Uniform + ?Sized>(&self, name: &str, value: &T) {
+        if let Some(location) = self.uniform_location(name) {
+            value.set_uniform(location);
         }
     }
 
-    pub fn set_mat4_f32(&self, name: &str, value: glm::Mat4) {
+    /// Uploads every member of `value` to the GLSL struct uniform `name`. The shader has to be
+    /// in use.
+    pub fn set_struct<T: UniformStruct + 'static>(&self, name: &str, value: &T) {
+        let mut struct_locations = self.struct_locations.borrow_mut();
+        let by_name = struct_locations.entry(TypeId::of::<T>()).or_default();
+        if !by_name.contains_key(name) {
+            let locations = T::field_names()
+                .iter()
+                .map(|field| self.uniform_location(&format!("{}.{}", name, field)))
+                .collect();
+            by_name.insert(name.to_string(), locations);
+        }
+        let mut locations = by_name[name].iter();
+        value.for_each_field(&mut |value| {
+            if let Some(Some(location)) = locations.next() {
+                value.set_uniform(*location);
+            }
+        });
+    }
+
+    /// Compares the members of `T` with the active uniforms of the struct uniform `name`.
+    ///
+    /// Returns the full names that exist on only one side, so a renamed GLSL member or a typo
+    /// in the Rust struct is caught when the shader is loaded instead of failing silently.
+    pub fn check_struct<T: UniformStruct>(&self, name: &str) -> Result<(), Vec<String>> {
+        let prefix = format!("{}.", name);
+        let mut mismatches: Vec<String> = T::field_names()
+            .iter()
+            .map(|field| format!("{}{}", prefix, field))
+            .filter(|field| !self.uniform_locations.contains_key(field))
+            .collect();
+        mismatches.extend(
+            self.uniforms
+                .iter()
+                .filter(|uniform| uniform.location >= 0)
+                .filter_map(|uniform| {
+                    let field = uniform.name.strip_prefix(&prefix)?;
+                    let field = field.strip_suffix("[0]").unwrap_or(field);
+                    (!T::field_names().contains(&field)).then(|| uniform.name.clone())
+                }),
+        );
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+}
+
+/// Collects the stages of a graphics program before compiling and linking them.
+///
+/// Stages are given as source paths; adding a stage twice replaces the earlier path.
+#[derive(Clone, Default, Debug)]
+pub struct ShaderBuilder {
+    stages: Vec<(ShaderStage, String)>,
+    defines: Defines,
+    cache: Option<ProgramCache>,
+}
+
+impl ShaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertex(self, path: &str) -> Self {
+        self.stage(ShaderStage::Vertex, path)
+    }
+
+    /// Needs OpenGL 4.0 or `ARB_tessellation_shader`, otherwise building fails with
+    /// [`ShaderError::Unsupported`].
+    pub fn tess_control(self, path: &str) -> Self {
+        self.stage(ShaderStage::TessControl, path)
+    }
+
+    /// Needs OpenGL 4.0 or `ARB_tessellation_shader`, like
+    /// [`tess_control`](Self::tess_control).
+    pub fn tess_evaluation(self, path: &str) -> Self {
+        self.stage(ShaderStage::TessEvaluation, path)
+    }
+
+    pub fn geometry(self, path: &str) -> Self {
+        self.stage(ShaderStage::Geometry, path)
+    }
+
+    pub fn fragment(self, path: &str) -> Self {
+        self.stage(ShaderStage::Fragment, path)
+    }
+
+    /// Compiles every stage with these `#define`s.
+    pub fn defines(mut self, defines: Defines) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// Loads the linked program from `cache` when neither the sources nor the driver changed,
+    /// and stores it there after linking otherwise.
+    pub fn cache(mut self, cache: ProgramCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn stage(mut self, stage: ShaderStage, path: &str) -> Self {
+        self.stages.retain(|(existing, _)| *existing != stage);
+        self.stages.push((stage, path.to_string()));
+        self
+    }
+
+    pub fn build(&self) -> Result<Shader, ShaderError> {
+        Shader::from_stages(&self.stages, &self.defines, self.cache.as_ref())
+    }
+
+    /// Checks the sources without compiling them, so it works without a GL context.
+    pub fn validate(&self) -> Result<(), Vec<GlslError>> {
+        glsl::validate(&self.stages, &self.defines)
+    }
+
+    pub fn build_reloadable(self) -> Result<ReloadableShader, ShaderError> {
+        let mut shader = ReloadableShader {
+            shader: self.build()?,
+            builder: self,
+            modified: Vec::new(),
+        };
+        shader.modified = shader.modification_times();
+        Ok(shader)
+    }
+}
+
+/// A shader that remembers where its sources came from and can rebuild itself when they change.
+///
+/// A failed rebuild leaves the previous program in place, so a broken edit never takes the
+/// running shader down with it.
+pub struct ReloadableShader {
+    shader: Shader,
+    builder: ShaderBuilder,
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ReloadableShader {
+    pub fn new(vertex_shader_path: &str, fragment_shader_path: &str) -> Result<Self, ShaderError> {
+        ShaderBuilder::new()
+            .vertex(vertex_shader_path)
+            .fragment(fragment_shader_path)
+            .build_reloadable()
+    }
+
+    /// Recompiles the program if any source file, included ones too, changed since the last
+    /// check.
+    ///
+    /// Returns `Ok(true)` when the program was swapped. Uniform values live in the program, so
+    /// callers have to set them again in that case.
+    pub fn reload_if_changed(&mut self) -> Result<bool, ShaderError> {
+        let modified = self.modification_times();
+        if modified == self.modified {
+            return Ok(false);
+        }
+        // Remember the new times even if the build fails, otherwise a broken file would be
+        // recompiled every frame until it is fixed.
+        self.modified = modified;
+        self.shader = self.builder.build()?;
+        // The new sources may include a different set of files.
+        self.modified = self.modification_times();
+        Ok(true)
+    }
+
+    fn modification_times(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        self.shader
+            .source_files()
+            .iter()
+            .map(|path| (path.clone(), shader_source::modified(path)))
+            .collect()
+    }
+}
+
+impl Deref for ReloadableShader {
+    type Target = Shader;
+
+    fn deref(&self) -> &Shader {
+        &self.shader
+    }
+}
+
+/// Compiles the same sources into several programs that differ by their `#define`s, for example
+/// with or without a specular map, and keeps each one around once it has been built.
+pub struct ShaderVariants {
+    builder: ShaderBuilder,
+    variants: HashMap<Defines, Shader>,
+}
+
+impl ShaderVariants {
+    /// `builder` describes the stages; its own defines are ignored in favour of the requested
+    /// ones.
+    pub fn new(builder: ShaderBuilder) -> Self {
+        Self {
+            builder,
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Returns the program compiled with `defines`, building it on first use.
+    pub fn get(&mut self, defines: &Defines) -> Result<&Shader, ShaderError> {
+        if !self.variants.contains_key(defines) {
+            let shader = self.builder.clone().defines(defines.clone()).build()?;
+            self.variants.insert(defines.clone(), shader);
+        }
+        Ok(&self.variants[defines])
+    }
+
+    /// Drops every compiled variant, so the next `get` rebuilds from the current sources.
+    pub fn clear(&mut self) {
+        self.variants.clear();
+    }
+}
+
+/// A program made of a single compute stage. Needs OpenGL 4.3 or `ARB_compute_shader`, more than
+/// the 3.3 context [`Engine`](crate::engine::Engine) asks for guarantees, so `new` fails with
+/// [`ShaderError::Unsupported`] without them.
+///
+/// Uniforms are set through the wrapped [`Shader`].
+pub struct ComputeShader {
+    shader: Shader,
+}
+
+impl ComputeShader {
+    pub fn new(path: &str) -> Result<Self, ShaderError> {
+        Ok(Self {
+            shader: Shader::from_stages(
+                &[(ShaderStage::Compute, path.to_string())],
+                &Defines::new(),
+                None,
+            )?,
+        })
+    }
+
+    /// The `local_size_x/y/z` declared by the shader.
+    pub fn local_size(&self) -> [i32; 3] {
+        let mut size = [0; 3];
         unsafe {
-            let location = gl::GetUniformLocation(self.id, to_cstring(name).as_ptr());
-            gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr());
+            gl::GetProgramiv(
+                self.shader.program.id(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                size.as_mut_ptr(),
+            );
         }
+        size
     }
 
-    pub fn set_3_f32(&self, name: &str, r: f32, g: f32, b: f32) {
+    /// Runs `x * y * z` work groups.
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.shader.use_shader();
         unsafe {
-            let location = gl::GetUniformLocation(self.id, to_cstring(name).as_ptr());
-            gl::Uniform3f(location, r, g, b);
+            gl::DispatchCompute(x, y, z);
         }
     }
 
-    pub fn set_vec3_f32(&self, name: &str, value: &glm::Vec3) {
+    /// Runs enough work groups to cover `x * y * z` invocations with the declared local size.
+    pub fn dispatch_invocations(&self, x: u32, y: u32, z: u32) {
+        let [local_x, local_y, local_z] = self.local_size().map(|size| size.max(1) as u32);
+        self.dispatch(
+            x.div_ceil(local_x),
+            y.div_ceil(local_y),
+            z.div_ceil(local_z),
+        );
+    }
+
+    /// Makes the writes of previous dispatches visible to the accesses in `barriers`, e.g.
+    /// `gl::SHADER_STORAGE_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT` before drawing
+    /// particles a compute pass has just simulated.
+    pub fn memory_barrier(barriers: gl::types::GLbitfield) {
         unsafe {
-            let location = gl::GetUniformLocation(self.id, to_cstring(name).as_ptr());
-            gl::Uniform3fv(location, 1, value.as_ptr());
+            gl::MemoryBarrier(barriers);
         }
     }
 }
 
-fn read_file(path: &str) -> CString {
-    let file = fs::read(path).expect(&format!("{} should be present", path));
-    let string;
-    unsafe {
-        string = CString::from_vec_unchecked(file);
+impl Deref for ComputeShader {
+    type Target = Shader;
+
+    fn deref(&self) -> &Shader {
+        &self.shader
     }
-    string
+}
+
+pub(crate) unsafe fn gl_version() -> (i32, i32) {
+    let (mut major, mut minor) = (0, 0);
+    gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+    gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    (major, minor)
+}
+
+pub(crate) unsafe fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    (0..count as gl::types::GLuint).any(|index| {
+        let extension = gl::GetStringi(gl::EXTENSIONS, index);
+        !extension.is_null() && CStr::from_ptr(extension as *const _).to_bytes() == name.as_bytes()
+    })
+}
+
+fn to_source_cstring(path: &str, source: &str) -> Result<CString, ShaderError> {
+    CString::new(source).map_err(|err| ShaderError::Io {
+        path: path.to_string(),
+        source: io::Error::new(io::ErrorKind::InvalidData, err),
+    })
+}
+
+/// Maps the names uniforms are set by to their locations. Arrays are only reported as `name[0]`,
+/// so `name` and every other element are looked up here; element locations are not guaranteed to
+/// be consecutive.
+unsafe fn uniform_locations(
+    program: gl::types::GLuint,
+    uniforms: &[UniformInfo],
+) -> HashMap<String, gl::types::GLint> {
+    let mut locations = HashMap::new();
+    for uniform in uniforms.iter().filter(|uniform| uniform.location >= 0) {
+        if let Some(base) = uniform.name.strip_suffix("[0]") {
+            locations.insert(base.to_string(), uniform.location);
+            for index in 1..uniform.size {
+                let element = format!("{}[{}]", base, index);
+                let location = gl::GetUniformLocation(program, to_cstring(&element).as_ptr());
+                if location >= 0 {
+                    locations.insert(element, location);
+                }
+            }
+        }
+        locations.insert(uniform.name.clone(), uniform.location);
+    }
+    locations
+}
+
+unsafe fn shader_info_log(shader: gl::types::GLuint) -> String {
+    let mut log_length = 0;
+    gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_length);
+    let mut info_log: Vec<u8> = vec![0; log_length.max(1) as usize];
+    let mut written = 0;
+    gl::GetShaderInfoLog(
+        shader,
+        info_log.len() as _,
+        &mut written,
+        info_log.as_mut_ptr() as _,
+    );
+    info_log.truncate(written as usize);
+    String::from_utf8_lossy(&info_log).into_owned()
+}
+
+unsafe fn program_info_log(program: gl::types::GLuint) -> String {
+    let mut log_length = 0;
+    gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_length);
+    let mut info_log: Vec<u8> = vec![0; log_length.max(1) as usize];
+    let mut written = 0;
+    gl::GetProgramInfoLog(
+        program,
+        info_log.len() as _,
+        &mut written,
+        info_log.as_mut_ptr() as _,
+    );
+    info_log.truncate(written as usize);
+    String::from_utf8_lossy(&info_log).into_owned()
 }
 
 #[inline]
-fn to_cstring(value: &str) -> CString {
+pub(crate) fn to_cstring(value: &str) -> CString {
     CString::new(value).unwrap()
 }
@@ -0,0 +1,67 @@
+//! The one place shader sources are read from.
+//!
+//! By default sources are read from disk, so edits are picked up by [`ReloadableShader`]. With
+//! the `embed-shaders` feature every file under `shader/` is baked into the binary instead, and
+//! the executable no longer has to be started from the crate root.
+//!
+//! [`ReloadableShader`]: crate::shader::ReloadableShader
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "embed-shaders")]
+static EMBEDDED: include_dir::Dir = include_dir::include_dir!("$CARGO_MANIFEST_DIR/shader");
+
+/// Reads a shader source, preferring the embedded copy when there is one.
+pub fn read(path: &Path) -> io::Result<String> {
+    if let Some(source) = embedded(path) {
+        return Ok(source.to_string());
+    }
+    fs::read_to_string(path)
+}
+
+/// Last modification time of a source on disk, or `None` for embedded sources, which never
+/// change.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    if embedded(path).is_some() {
+        return None;
+    }
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Resolves `.` and `..` without touching the file system, which embedded files are not on.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(feature = "embed-shaders")]
+fn embedded(path: &Path) -> Option<&'static str> {
+    let path = normalize(path);
+    let relative = path
+        .strip_prefix(concat!(env!("CARGO_MANIFEST_DIR"), "/shader"))
+        .or_else(|_| path.strip_prefix("shader"))
+        .ok()?;
+    EMBEDDED.get_file(relative)?.contents_utf8()
+}
+
+#[cfg(not(feature = "embed-shaders"))]
+fn embedded(_path: &Path) -> Option<&'static str> {
+    None
+}
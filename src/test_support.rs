@@ -0,0 +1,18 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes `files` to a fresh temporary directory named after `test` and returns its path.
+///
+/// File names may contain directories, which are created as needed.
+pub fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (name, source) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    dir
+}
@@ -0,0 +1,154 @@
+/// A value that can be uploaded to a GLSL uniform with [`Shader::set`](crate::shader::Shader::set).
+///
+/// Implementations upload to the currently bound program, so the shader has to be in use.
+pub trait Uniform {
+    fn set_uniform(&self, location: gl::types::GLint);
+}
+
+impl Uniform for bool {
+    fn set_uniform(&self, location: gl::types::GLint) {
+        unsafe {
+            gl::Uniform1i(location, *self as _);
+        }
+    }
+}
+
+impl Uniform for [bool] {
+    fn set_uniform(&self, location: gl::types::GLint) {
+        let values: Vec<i32> = self.iter().map(|value| *value as i32).collect();
+        values.set_uniform(location);
+    }
+}
+
+impl<T: Uniform, const N: usize> Uniform for [T; N]
+where
+    [T]: Uniform,
+{
+    fn set_uniform(&self, location: gl::types::GLint) {
+        self[..].set_uniform(location);
+    }
+}
+
+impl<T> Uniform for Vec<T>
+where
+    [T]: Uniform,
+{
+    fn set_uniform(&self, location: gl::types::GLint) {
+        self[..].set_uniform(location);
+    }
+}
+
+/// Implements `Uniform` for a scalar, vector or matrix type and for slices of it, which map to
+/// GLSL arrays. `$components` is the number of `$scalar`s in one value.
+macro_rules! uniform {
+    ($type:ty, $scalar:ty, $components:expr, $function:ident) => {
+        uniform!(@impl $type, $scalar, $components, |location, count, ptr| unsafe {
+            gl::$function(location, count, ptr)
+        });
+    };
+    ($type:ty, $scalar:ty, $components:expr, $function:ident, matrix) => {
+        uniform!(@impl $type, $scalar, $components, |location, count, ptr| unsafe {
+            gl::$function(location, count, gl::FALSE, ptr)
+        });
+    };
+    (@impl $type:ty, $scalar:ty, $components:expr,
+        |$location:ident, $count:ident, $ptr:ident| $upload:expr) => {
+        impl Uniform for $type {
+            fn set_uniform(&self, location: gl::types::GLint) {
+                std::slice::from_ref(self).set_uniform(location);
+            }
+        }
+
+        impl Uniform for [$type] {
+            fn set_uniform(&self, $location: gl::types::GLint) {
+                debug_assert_eq!(
+                    std::mem::size_of::<$type>(),
+                    $components * std::mem::size_of::<$scalar>()
+                );
+                let $count = self.len() as gl::types::GLsizei;
+                let $ptr = self.as_ptr() as *const $scalar;
+                $upload;
+            }
+        }
+    };
+}
+
+uniform!(f32, f32, 1, Uniform1fv);
+uniform!(glm::Vec2, f32, 2, Uniform2fv);
+uniform!(glm::Vec3, f32, 3, Uniform3fv);
+uniform!(glm::Vec4, f32, 4, Uniform4fv);
+uniform!(i32, i32, 1, Uniform1iv);
+uniform!(glm::IVec2, i32, 2, Uniform2iv);
+uniform!(glm::IVec3, i32, 3, Uniform3iv);
+uniform!(glm::IVec4, i32, 4, Uniform4iv);
+uniform!(u32, u32, 1, Uniform1uiv);
+uniform!(glm::UVec2, u32, 2, Uniform2uiv);
+uniform!(glm::UVec3, u32, 3, Uniform3uiv);
+uniform!(glm::UVec4, u32, 4, Uniform4uiv);
+uniform!(glm::Mat2, f32, 4, UniformMatrix2fv, matrix);
+uniform!(glm::Mat3, f32, 9, UniformMatrix3fv, matrix);
+uniform!(glm::Mat4, f32, 16, UniformMatrix4fv, matrix);
+
+/// A Rust struct mirroring a GLSL struct, uploaded member by member with
+/// [`Shader::set_struct`](crate::shader::Shader::set_struct).
+///
+/// Implement it with [`uniform_struct!`](crate::uniform_struct) rather than by hand.
+pub trait UniformStruct {
+    /// GLSL names of the struct members, in declaration order.
+    fn field_names() -> &'static [&'static str];
+
+    /// Calls `f` with the value of every member, in the order of
+    /// [`field_names`](Self::field_names).
+    fn for_each_field(&self, f: &mut dyn FnMut(&dyn Uniform));
+}
+
+/// Declares a struct and implements [`UniformStruct`] for it.
+///
+/// Members are named after the Rust field unless a `#[glsl = "name"]` attribute says otherwise:
+///
+/// ```ignore
+/// uniform_struct! {
+///     pub struct SpotLight {
+///         pub position: glm::Vec3,
+///         #[glsl = "cutOff"]
+///         pub cut_off: f32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! uniform_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[glsl = $glsl:literal])?
+                $field_vis:vis $field:ident: $type:ty,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[doc = $doc])*
+                $field_vis $field: $type,
+            )*
+        }
+
+        impl $crate::uniform::UniformStruct for $name {
+            fn field_names() -> &'static [&'static str] {
+                &[$($crate::uniform_struct!(@name $field $($glsl)?),)*]
+            }
+
+            fn for_each_field(&self, f: &mut dyn FnMut(&dyn $crate::uniform::Uniform)) {
+                $(f(&self.$field);)*
+            }
+        }
+    };
+    (@name $field:ident) => {
+        stringify!($field)
+    };
+    (@name $field:ident $glsl:literal) => {
+        $glsl
+    };
+}
@@ -0,0 +1,329 @@
+use std::marker::PhantomData;
+
+use crate::gl_object::Buffer;
+use crate::shader::Shader;
+
+/// A value that can be written into a uniform buffer with the std140 layout rules.
+pub trait Std140 {
+    /// Base alignment in bytes.
+    const ALIGNMENT: usize;
+
+    /// Appends the value, including any padding inside it, to `out`.
+    fn write_std140(&self, out: &mut Vec<u8>);
+}
+
+impl Std140 for f32 {
+    const ALIGNMENT: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for i32 {
+    const ALIGNMENT: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for u32 {
+    const ALIGNMENT: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for bool {
+    const ALIGNMENT: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        (*self as u32).write_std140(out);
+    }
+}
+
+impl Std140 for glm::Vec2 {
+    const ALIGNMENT: usize = 8;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|value| value.write_std140(out));
+    }
+}
+
+impl Std140 for glm::Vec3 {
+    const ALIGNMENT: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|value| value.write_std140(out));
+    }
+}
+
+impl Std140 for glm::Vec4 {
+    const ALIGNMENT: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|value| value.write_std140(out));
+    }
+}
+
+impl Std140 for glm::Mat3 {
+    const ALIGNMENT: usize = 16;
+
+    /// Every column is padded to a `vec4`.
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        for column in self.column_iter() {
+            column.iter().for_each(|value| value.write_std140(out));
+            0_f32.write_std140(out);
+        }
+    }
+}
+
+impl Std140 for glm::Mat4 {
+    const ALIGNMENT: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|value| value.write_std140(out));
+    }
+}
+
+/// Arrays have a stride of a whole `vec4` multiple, so every element is padded to 16 bytes.
+impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGNMENT: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        for element in self {
+            element.write_std140(out);
+            pad_to(out, 16);
+        }
+    }
+}
+
+/// Appends `value` to `out` at its std140 offset.
+pub fn write_member<T: Std140>(out: &mut Vec<u8>, value: &T) {
+    pad_to(out, T::ALIGNMENT);
+    value.write_std140(out);
+}
+
+/// Pads `out` to the next multiple of `alignment`.
+pub fn pad_to(out: &mut Vec<u8>, alignment: usize) {
+    let padded = out.len().div_ceil(alignment) * alignment;
+    out.resize(padded, 0);
+}
+
+/// A Rust struct mirroring a `layout (std140) uniform` block.
+///
+/// Implement it with [`uniform_block!`](crate::uniform_block) rather than by hand.
+pub trait UniformBlock {
+    /// Name of the block in GLSL.
+    const NAME: &'static str;
+
+    /// Serializes the block with the std140 layout.
+    fn to_std140(&self) -> Vec<u8>;
+}
+
+/// Declares a struct and implements [`UniformBlock`] for it. Members are laid out in declaration
+/// order, so they have to be listed in the same order as in the GLSL block.
+///
+/// ```ignore
+/// uniform_block! {
+///     pub struct CameraBlock: "Camera" {
+///         pub view: glm::Mat4,
+///         pub projection: glm::Mat4,
+///     }
+/// }
+/// ```
+///
+/// Without the block name only [`Std140`] is implemented, for structs nested in a block.
+#[macro_export]
+macro_rules! uniform_block {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident: $block:literal {
+            $($fields:tt)*
+        }
+    ) => {
+        $crate::uniform_block! {
+            $(#[$meta])*
+            $vis struct $name {
+                $($fields)*
+            }
+        }
+
+        impl $crate::uniform_buffer::UniformBlock for $name {
+            const NAME: &'static str = $block;
+
+            fn to_std140(&self) -> Vec<u8> {
+                let mut out = Vec::new();
+                $crate::uniform_buffer::Std140::write_std140(self, &mut out);
+                out
+            }
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $field_vis:vis $field:ident: $type:ty,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[doc = $doc])*
+                $field_vis $field: $type,
+            )*
+        }
+
+        /// Structs are aligned and padded to a `vec4`.
+        impl $crate::uniform_buffer::Std140 for $name {
+            const ALIGNMENT: usize = 16;
+
+            fn write_std140(&self, out: &mut Vec<u8>) {
+                $($crate::uniform_buffer::write_member(out, &self.$field);)*
+                $crate::uniform_buffer::pad_to(out, 16);
+            }
+        }
+    };
+}
+
+/// A uniform buffer holding one `T` block, bound to a fixed binding point.
+///
+/// The same buffer is shared by every shader it is attached to, so data common to all programs,
+/// like the camera matrices, is uploaded once per frame.
+pub struct UniformBuffer<T: UniformBlock> {
+    buffer: Buffer,
+    binding: gl::types::GLuint,
+    size: usize,
+    block: PhantomData<T>,
+}
+
+impl<T: UniformBlock> UniformBuffer<T> {
+    pub fn new(binding: gl::types::GLuint, value: &T) -> Self {
+        let data = value.to_std140();
+        let buffer = Buffer::new();
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer.id());
+            buffer.label(T::NAME);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                data.len() as _,
+                data.as_ptr() as _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, buffer.id());
+        }
+        Self {
+            buffer,
+            binding,
+            size: data.len(),
+            block: PhantomData,
+        }
+    }
+
+    pub fn update(&self, value: &T) {
+        let data = value.to_std140();
+        debug_assert_eq!(data.len(), self.size);
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer.id());
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, data.len() as _, data.as_ptr() as _);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    /// Points the block of `shader` at this buffer. Returns `false` if the shader does not
+    /// declare the block.
+    ///
+    /// Block bindings live in the program, so this has to be repeated after a reload.
+    pub fn attach(&self, shader: &Shader) -> bool {
+        shader.bind_uniform_block(T::NAME, self.binding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f32_at(data: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    crate::uniform_block! {
+        struct Light {
+            direction: glm::Vec3,
+            intensity: f32,
+        }
+    }
+
+    crate::uniform_block! {
+        struct Scene: "Scene" {
+            position: glm::Vec3,
+            time: f32,
+            normal: glm::Mat3,
+            scale: f32,
+            weights: [f32; 3],
+            offsets: [glm::Vec3; 2],
+            exposure: f32,
+            light: Light,
+            gamma: f32,
+        }
+    }
+
+    #[test]
+    fn members_are_written_at_their_std140_offsets() {
+        let scene = Scene {
+            position: glm::vec3(1., 2., 3.),
+            time: 4.,
+            normal: glm::mat3(5., 8., 11., 6., 9., 12., 7., 10., 13.),
+            scale: 14.,
+            weights: [15., 16., 17.],
+            offsets: [glm::vec3(18., 19., 20.), glm::vec3(21., 22., 23.)],
+            exposure: 24.,
+            light: Light {
+                direction: glm::vec3(25., 26., 27.),
+                intensity: 28.,
+            },
+            gamma: 29.,
+        };
+        let data = scene.to_std140();
+        let expected = [
+            // A float packs right after a vec3.
+            (0, 1.),
+            (4, 2.),
+            (8, 3.),
+            (12, 4.),
+            // mat3 columns are padded to vec4s.
+            (16, 5.),
+            (20, 6.),
+            (24, 7.),
+            (28, 0.),
+            (32, 8.),
+            (48, 11.),
+            (56, 13.),
+            (60, 0.),
+            (64, 14.),
+            // Array elements have a 16 byte stride, even for floats.
+            (80, 15.),
+            (96, 16.),
+            (112, 17.),
+            (128, 18.),
+            (136, 20.),
+            (144, 21.),
+            (152, 23.),
+            (160, 24.),
+            // Nested structs start and end on a vec4 boundary.
+            (176, 25.),
+            (184, 27.),
+            (188, 28.),
+            (192, 29.),
+        ];
+        for (offset, value) in expected {
+            assert_eq!(f32_at(&data, offset), value, "at offset {}", offset);
+        }
+        assert_eq!(data.len(), 208);
+    }
+}
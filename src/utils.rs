@@ -1,22 +1,103 @@
-#[derive(Clone, PartialEq, Eq, Debug)]
+use std::collections::HashMap;
+
+use glfw::{Action, GamepadAxis, GamepadButton, GamepadState, Key, MouseButton, WindowEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::DeadZones;
+
+const GAMEPAD_BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+/// A button over one update.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct ButtonState {
+    /// Presses and releases since the last update. Several fast taps between two updates all
+    /// count, so none is lost.
     pub half_transition_count: i32,
     pub ended_down: bool,
+    /// Seconds the button has been down before this update, 0 if it was pressed during it.
+    pub held_time: f32,
 }
 
-#[derive(Clone, Debug)]
-pub struct Input {
-    pub up: ButtonState,
-    pub down: ButtonState,
-    pub left: ButtonState,
-    pub right: ButtonState,
+impl ButtonState {
+    /// Whether the button went down since the last update, even if it is up again already.
+    pub fn was_pressed(&self) -> bool {
+        self.half_transition_count > 1 || (self.half_transition_count == 1 && self.ended_down)
+    }
 
-    pub left_bracket: ButtonState,
-    pub right_bracket: ButtonState,
+    /// Whether the button went up since the last update, even if it is down again already.
+    pub fn was_released(&self) -> bool {
+        self.half_transition_count > 1 || (self.half_transition_count == 1 && !self.ended_down)
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.ended_down
+    }
 
-    pub mouse_left: ButtonState,
+    /// Whether the button was down at the start of the update.
+    pub fn started_down(&self) -> bool {
+        self.ended_down != (self.half_transition_count % 2 == 1)
+    }
+
+    fn process(&mut self, action: Action) {
+        let down = match action {
+            Action::Press => true,
+            Action::Release => false,
+            Action::Repeat => return,
+        };
+        if down != self.ended_down {
+            self.ended_down = down;
+            self.half_transition_count += 1;
+            if down {
+                self.held_time = 0.;
+            }
+        }
+    }
 
+    fn advance(&mut self, dt: f32) {
+        self.half_transition_count = 0;
+        self.held_time = if self.ended_down {
+            self.held_time + dt
+        } else {
+            0.
+        };
+    }
+}
+
+/// State of the devices. What the buttons mean is up to an
+/// [`InputMap`](crate::input_map::InputMap).
+#[derive(Clone, Debug)]
+pub struct Input {
+    /// Keys that were pressed at least once. Missing keys are up.
+    pub keys: HashMap<Key, ButtonState>,
+    pub mouse_buttons: HashMap<MouseButton, ButtonState>,
+    pub gamepad_buttons: HashMap<GamepadButton, ButtonState>,
+    /// Indexed by `GamepadAxis`. Sticks are in `[-1, 1]` with -1 being up and left, triggers
+    /// are in `[0, 1]`. Both are 0 inside their dead zone.
+    pub gamepad_axes: [f32; 6],
+    pub gamepad_dead_zones: DeadZones,
+    pub gamepad_connected: bool,
+
+    /// Cursor position in screen coordinates. With a disabled cursor it is not bounded by the
+    /// window and only the movement is meaningful.
     pub mouse: glm::Vec2,
+    /// Cursor movement since the last update, summed over all the events in between.
+    pub mouse_delta: glm::Vec2,
 
     pub mouse_scroll: f32,
 
@@ -26,37 +107,176 @@ pub struct Input {
 impl Input {
     pub fn new(mouse_x: f32, mouse_y: f32) -> Self {
         Self {
-            up: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            down: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            left: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            right: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            left_bracket: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            right_bracket: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
-            mouse_left: ButtonState {
-                half_transition_count: 0,
-                ended_down: false,
-            },
+            keys: HashMap::new(),
+            mouse_buttons: HashMap::new(),
+            gamepad_buttons: HashMap::new(),
+            gamepad_axes: [0.; 6],
+            gamepad_dead_zones: DeadZones::default(),
+            gamepad_connected: false,
             mouse: glm::vec2(mouse_x, mouse_y),
+            mouse_delta: glm::vec2(0., 0.),
             mouse_scroll: 0.,
             delta_time: 0.,
         }
     }
+
+    pub fn key(&self, key: Key) -> ButtonState {
+        self.keys.get(&key).copied().unwrap_or_default()
+    }
+
+    pub fn mouse_button(&self, button: MouseButton) -> ButtonState {
+        self.mouse_buttons.get(&button).copied().unwrap_or_default()
+    }
+
+    pub fn gamepad_button(&self, button: GamepadButton) -> ButtonState {
+        self.gamepad_buttons
+            .get(&button)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes[axis as usize]
+    }
+
+    /// Folds a window event into the button states, the cursor position, and the cursor
+    /// movement and scroll amount, which add up until they are reset.
+    pub fn process_event(&mut self, event: &WindowEvent) {
+        match *event {
+            // Every key GLFW has no name for is `Key::Unknown`, so their presses and releases
+            // would mix up as if they were one key.
+            WindowEvent::Key(Key::Unknown, ..) => {}
+            WindowEvent::Key(key, _, action, _) => {
+                self.keys.entry(key).or_default().process(action);
+            }
+            WindowEvent::MouseButton(button, action, _) => {
+                self.mouse_buttons
+                    .entry(button)
+                    .or_default()
+                    .process(action);
+            }
+            WindowEvent::CursorPos(x, y) => {
+                let position = glm::vec2(x as f32, y as f32);
+                self.mouse_delta += position - self.mouse;
+                self.mouse = position;
+            }
+            WindowEvent::Scroll(_, y_offset) => {
+                self.mouse_scroll += y_offset as f32;
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts the next update: clears the transitions, the cursor movement and the scroll
+    /// amount, and adds `dt` to the time the held buttons are down.
+    pub fn advance(&mut self, dt: f32) {
+        let buttons = self
+            .keys
+            .values_mut()
+            .chain(self.mouse_buttons.values_mut())
+            .chain(self.gamepad_buttons.values_mut());
+        for button in buttons {
+            button.advance(dt);
+        }
+        self.mouse_delta = glm::vec2(0., 0.);
+        self.mouse_scroll = 0.;
+    }
+
+    /// Takes over the polled state of the gamepad, or releases everything for `None`.
+    pub fn process_gamepad(&mut self, state: Option<&GamepadState>) {
+        for button in GAMEPAD_BUTTONS {
+            let action = state.map_or(Action::Release, |state| state.get_button_state(button));
+            self.gamepad_buttons
+                .entry(button)
+                .or_default()
+                .process(action);
+        }
+        self.gamepad_connected = state.is_some();
+        self.gamepad_axes = match state {
+            Some(state) => {
+                let zones = self.gamepad_dead_zones;
+                let (left_x, left_y) = zones.apply_to_stick(
+                    state.get_axis(GamepadAxis::AxisLeftX),
+                    state.get_axis(GamepadAxis::AxisLeftY),
+                );
+                let (right_x, right_y) = zones.apply_to_stick(
+                    state.get_axis(GamepadAxis::AxisRightX),
+                    state.get_axis(GamepadAxis::AxisRightY),
+                );
+                [
+                    left_x,
+                    left_y,
+                    right_x,
+                    right_y,
+                    zones.apply_to_trigger(state.get_axis(GamepadAxis::AxisLeftTrigger)),
+                    zones.apply_to_trigger(state.get_axis(GamepadAxis::AxisRightTrigger)),
+                ]
+            }
+            None => [0.; 6],
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(actions: &[Action]) -> ButtonState {
+        let mut state = ButtonState::default();
+        for action in actions {
+            state.process(*action);
+        }
+        state
+    }
+
+    #[test]
+    fn taps_between_two_updates_all_count() {
+        let tap = button(&[Action::Press, Action::Release]);
+        assert_eq!(tap.half_transition_count, 2);
+        assert!(tap.was_pressed() && tap.was_released());
+        assert!(!tap.is_held() && !tap.started_down());
+
+        let taps = button(&[Action::Press, Action::Release, Action::Press]);
+        assert_eq!(taps.half_transition_count, 3);
+        assert!(taps.was_pressed() && taps.was_released());
+        assert!(taps.is_held() && !taps.started_down());
+    }
+
+    #[test]
+    fn repeats_and_unchanged_actions_are_not_transitions() {
+        let held = button(&[Action::Press, Action::Repeat, Action::Press, Action::Repeat]);
+        assert_eq!(held.half_transition_count, 1);
+        assert!(held.was_pressed() && !held.was_released());
+
+        let released = button(&[Action::Release]);
+        assert_eq!(released, ButtonState::default());
+    }
+
+    #[test]
+    fn advancing_keeps_the_button_down_and_counts_the_held_time() {
+        let mut state = button(&[Action::Press]);
+        assert_eq!(state.held_time, 0.);
+
+        state.advance(0.25);
+        assert_eq!(state.half_transition_count, 0);
+        assert!(state.started_down() && state.is_held() && !state.was_pressed());
+        assert_eq!(state.held_time, 0.25);
+        state.advance(0.25);
+        assert_eq!(state.held_time, 0.5);
+
+        state.process(Action::Release);
+        assert!(state.started_down() && state.was_released());
+        state.advance(0.25);
+        assert_eq!(state, ButtonState::default());
+    }
+
+    #[test]
+    fn pressing_again_restarts_the_held_time() {
+        let mut state = button(&[Action::Press]);
+        state.advance(1.);
+        state.process(Action::Release);
+        state.process(Action::Press);
+        assert_eq!(state.held_time, 0.);
+        assert!(state.started_down() && state.was_pressed() && state.was_released());
+    }
 }
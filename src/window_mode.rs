@@ -0,0 +1,227 @@
+//! Windowed, exclusive fullscreen and borderless window modes on any connected monitor.
+
+use std::fmt;
+
+use glfw::{Monitor, VidMode, Window};
+
+/// Which connected monitor a mode uses.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum MonitorChoice {
+    #[default]
+    Primary,
+    /// Position in the list of connected monitors, where the primary one comes first.
+    Index(usize),
+    /// The name the platform gives the monitor, as printed in the error when nothing matches.
+    Name(String),
+}
+
+impl fmt::Display for MonitorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorChoice::Primary => f.write_str("primary monitor"),
+            MonitorChoice::Index(index) => write!(f, "monitor {}", index),
+            MonitorChoice::Name(name) => write!(f, "monitor `{}`", name),
+        }
+    }
+}
+
+/// Resolution and refresh rate of an exclusive fullscreen mode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VideoMode {
+    pub width: u32,
+    pub height: u32,
+    /// `None` picks the highest rate the monitor offers at this resolution.
+    pub refresh_rate: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum WindowMode {
+    /// A decorated window with the size of `EngineConfig`.
+    #[default]
+    Windowed,
+    /// Takes over the monitor and switches it to `video_mode`, or keeps its current mode for
+    /// `None`. Switching in and out is slower than with `Borderless`, but the compositor is
+    /// bypassed.
+    Fullscreen {
+        monitor: MonitorChoice,
+        video_mode: Option<VideoMode>,
+    },
+    /// An undecorated window covering the monitor at its current mode.
+    Borderless { monitor: MonitorChoice },
+}
+
+#[derive(Debug)]
+pub enum WindowModeError {
+    NoMonitor(MonitorChoice, Vec<String>),
+}
+
+impl fmt::Display for WindowModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowModeError::NoMonitor(choice, connected) => {
+                write!(f, "no {} is connected", choice)?;
+                if !connected.is_empty() {
+                    write!(f, ", connected monitors: {}", connected.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for WindowModeError {}
+
+/// Switches a window between modes and remembers where it was windowed, so it returns there.
+pub struct WindowModes {
+    current: WindowMode,
+    fullscreen: WindowMode,
+    windowed: (i32, i32, u32, u32),
+}
+
+impl WindowModes {
+    /// Starts from the windowed `window`. `fullscreen` is the mode [`toggle`](Self::toggle)
+    /// switches to.
+    pub fn new(window: &Window, fullscreen: WindowMode) -> Self {
+        Self {
+            current: WindowMode::Windowed,
+            fullscreen,
+            windowed: windowed_geometry(window),
+        }
+    }
+
+    pub fn current(&self) -> &WindowMode {
+        &self.current
+    }
+
+    pub fn set(&mut self, window: &mut Window, mode: WindowMode) -> Result<(), WindowModeError> {
+        if mode == self.current {
+            return Ok(());
+        }
+        if self.current == WindowMode::Windowed {
+            self.windowed = windowed_geometry(window);
+        }
+
+        match &mode {
+            WindowMode::Windowed => {
+                let (x, y, width, height) = self.windowed;
+                window.set_decorated(true);
+                window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+            }
+            WindowMode::Fullscreen {
+                monitor,
+                video_mode,
+            } => {
+                let mut glfw = window.glfw.clone();
+                glfw.with_connected_monitors(|_, monitors| {
+                    let monitor = find_monitor(monitors, monitor)?;
+                    let (width, height) = self.windowed_size();
+                    let (width, height, refresh_rate) = choose_video_mode(monitor, *video_mode)
+                        .map_or((width, height, None), |mode| {
+                            (mode.width, mode.height, Some(mode.refresh_rate))
+                        });
+                    window.set_monitor(
+                        glfw::WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        width,
+                        height,
+                        refresh_rate,
+                    );
+                    Ok(())
+                })?;
+            }
+            WindowMode::Borderless { monitor } => {
+                let mut glfw = window.glfw.clone();
+                glfw.with_connected_monitors(|_, monitors| {
+                    let monitor = find_monitor(monitors, monitor)?;
+                    let (x, y) = monitor.get_pos();
+                    let (width, height) = monitor
+                        .get_video_mode()
+                        .map_or(self.windowed_size(), |mode| (mode.width, mode.height));
+                    window.set_decorated(false);
+                    window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+                    Ok(())
+                })?;
+            }
+        }
+        self.current = mode;
+        Ok(())
+    }
+
+    /// Switches between windowed and the fullscreen mode given to [`new`](Self::new).
+    pub fn toggle(&mut self, window: &mut Window) -> Result<(), WindowModeError> {
+        let mode = if self.current == WindowMode::Windowed {
+            self.fullscreen.clone()
+        } else {
+            WindowMode::Windowed
+        };
+        self.set(window, mode)
+    }
+
+    fn windowed_size(&self) -> (u32, u32) {
+        (self.windowed.2, self.windowed.3)
+    }
+}
+
+fn windowed_geometry(window: &Window) -> (i32, i32, u32, u32) {
+    let (x, y) = window.get_pos();
+    let (width, height) = window.get_size();
+    (x, y, width.max(1) as u32, height.max(1) as u32)
+}
+
+fn find_monitor<'a>(
+    monitors: &'a [Monitor],
+    choice: &MonitorChoice,
+) -> Result<&'a Monitor, WindowModeError> {
+    let monitor = match choice {
+        MonitorChoice::Primary => monitors.first(),
+        MonitorChoice::Index(index) => monitors.get(*index),
+        MonitorChoice::Name(name) => monitors
+            .iter()
+            .find(|monitor| monitor.get_name().as_deref() == Some(name.as_str())),
+    };
+    monitor.ok_or_else(|| {
+        WindowModeError::NoMonitor(
+            choice.clone(),
+            monitors
+                .iter()
+                .map(|monitor| monitor.get_name().unwrap_or_default())
+                .collect(),
+        )
+    })
+}
+
+/// The monitor mode matching `requested`, or the current mode if there is none.
+fn choose_video_mode(monitor: &Monitor, requested: Option<VideoMode>) -> Option<VidMode> {
+    let current = monitor.get_video_mode();
+    let chosen = requested.and_then(|requested| {
+        let chosen = monitor
+            .get_video_modes()
+            .into_iter()
+            .filter(|mode| mode.width == requested.width && mode.height == requested.height)
+            .filter(|mode| {
+                requested
+                    .refresh_rate
+                    .is_none_or(|rate| mode.refresh_rate == rate)
+            })
+            .max_by_key(|mode| {
+                (
+                    mode.refresh_rate,
+                    mode.red_bits + mode.green_bits + mode.blue_bits,
+                )
+            });
+        if chosen.is_none() {
+            log::warn!(
+                "{} has no {}x{} mode{}, keeping its current mode",
+                monitor.get_name().unwrap_or_default(),
+                requested.width,
+                requested.height,
+                requested
+                    .refresh_rate
+                    .map_or(String::new(), |rate| format!(" at {} Hz", rate))
+            );
+        }
+        chosen
+    });
+    chosen.or(current)
+}